@@ -0,0 +1,193 @@
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::{web, Error, HttpMessage, ResponseError};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::future::{ready, Future, Ready};
+use std::path::Path;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::str::FromStr;
+use crate::cache::RouteClass;
+use crate::config::read_file;
+use crate::errors::ApiError;
+
+/// Route groups an api key can be allowed to call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Translations, texts, strong numbers and statistics
+    Read,
+    Search,
+    /// The endpoints below `/api/v1/admin`
+    Admin,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKey {
+    pub key: String,
+    /// Name of the client, used in the logs
+    pub name: String,
+    pub scopes: Vec<Scope>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiKeyFile {
+    keys: Vec<ApiKey>,
+}
+
+/// The client authenticated by its api key, stored in the request extensions.
+#[derive(Clone)]
+pub struct ApiClient(pub String);
+
+/// The api keys of the clients together with the route groups which require one.
+pub struct ApiKeys {
+    keys: HashMap<String, ApiKey>,
+    protected: HashSet<Scope>,
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "read" => Ok(Self::Read),
+            "search" => Ok(Self::Search),
+            "admin" => Ok(Self::Admin),
+            _ => Err(format!("Unknown scope '{}', expected read, search or admin.", s)),
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read => write!(f, "read"),
+            Self::Search => write!(f, "search"),
+            Self::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl From<RouteClass> for Scope {
+    fn from(class: RouteClass) -> Self {
+        match class {
+            RouteClass::Search => Self::Search,
+            _ => Self::Read,
+        }
+    }
+}
+
+impl ApiKeys {
+    /// Reads the keys from a yaml or toml file with a `keys` list of `{key, name, scopes}`.
+    pub fn from_file(path: &Path, protected: &[Scope]) -> Result<Self, String> {
+        let file: ApiKeyFile = read_file(path)?;
+        let mut keys = HashMap::new();
+        for key in file.keys {
+            if key.key.trim().len() < 16 {
+                return Err(format!("{}: the key of '{}' must have at least 16 characters", path.display(), key.name));
+            }
+            if let Some(other) = keys.insert(key.key.clone(), key) {
+                return Err(format!("{}: the key of '{}' is used twice", path.display(), other.name));
+            }
+        }
+        Ok(Self { keys, protected: protected.iter().copied().collect() })
+    }
+
+    pub fn protects(&self, scope: Scope) -> bool {
+        self.protected.contains(&scope)
+    }
+
+    /// The key sent as `Authorization: Bearer <key>` or `X-API-Key: <key>` header.
+    fn request_key(req: &ServiceRequest) -> Option<&str> {
+        let headers = req.headers();
+        headers.get(header::AUTHORIZATION)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.strip_prefix("Bearer "))
+            .or_else(|| headers.get("X-API-Key").and_then(|x| x.to_str().ok()))
+            .map(|x| x.trim())
+    }
+
+    /// Checks whether the request may call a route of the given group, returning
+    /// the client of a valid key.
+    fn authorize(&self, req: &ServiceRequest, scope: Scope) -> Result<Option<ApiClient>, ApiError> {
+        let key = Self::request_key(req);
+        if !self.protects(scope) {
+            return Ok(key.and_then(|x| self.keys.get(x)).map(|x| ApiClient(x.name.clone())));
+        }
+        let key = key.ok_or_else(|| ApiError::unauthorized(String::from("An api key is required.")))?;
+        let key = self.keys.get(key)
+            .ok_or_else(|| ApiError::unauthorized(String::from("The api key is invalid.")))?;
+        if !key.scopes.contains(&scope) {
+            return Err(ApiError::forbidden(format!("The api key is not allowed to use the {} endpoints.", scope)));
+        }
+        Ok(Some(ApiClient(key.name.clone())))
+    }
+}
+
+/// Rejects requests to a route group protected by the `ApiKeys` of the app
+/// without a key allowed to call it. Without `ApiKeys` every request passes.
+pub struct RequireScope {
+    scope: Scope,
+}
+
+impl RequireScope {
+    pub fn new(scope: Scope) -> Self {
+        Self { scope }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireScope
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequireScopeMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireScopeMiddleware { service: Rc::new(service), scope: self.scope }))
+    }
+}
+
+pub struct RequireScopeMiddleware<S> {
+    service: Rc<S>,
+    scope: Scope,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireScopeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let authorized = match req.app_data::<web::Data<ApiKeys>>() {
+            Some(keys) => keys.authorize(&req, self.scope),
+            None => Ok(None),
+        };
+
+        Box::pin(async move {
+            match authorized {
+                Ok(Some(client)) => { req.extensions_mut().insert(client); },
+                Ok(None) => {},
+                Err(e) => return Ok(req.into_response(e.error_response()).map_into_right_body()),
+            }
+            Ok(service.call(req).await?.map_into_left_body())
+        })
+    }
+}
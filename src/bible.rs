@@ -1,14 +1,34 @@
 pub mod constants;
 pub mod zefania_impl;
 pub mod traits;
-
-use crate::BOOKS;
-use serde::{Serialize, Serializer};
-use std::cell::{RefCell};
+pub mod reference;
+pub mod cooccurrence;
+pub mod tokens;
+pub mod stats;
+pub mod concordance;
+pub mod passage;
+pub mod versification;
+pub mod compare;
+pub mod diff;
+pub mod render;
+pub mod validate;
+pub mod convert;
+pub mod osis_impl;
+pub mod usfm_impl;
+pub mod json_impl;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_impl;
+pub mod text_impl;
+
+use crate::{BookRange, ParseReferenceError, Versification, format_reference};
+use serde::{Deserialize, Serialize, Serializer};
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 
 // Declarations
 #[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct Translation {
     pub identifier: String,
     pub name: String,
@@ -21,59 +41,119 @@ pub struct Bible {
     pub books: Vec<Book>,
     pub greek_strong_dict: HashMap<usize, StrongDictEntry>,
     pub hebrew_strong_dict: HashMap<usize, StrongDictEntry>,
+    #[serde(skip)]
+    pub versification: Versification,
+    /// Hash of the source file, changes whenever the text or tags change
+    #[serde(skip)]
+    pub content_hash: u64,
 }
 
 #[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct StrongDictEntry {
     pub variants: Vec<StrongVariant>,
     pub refs: Vec<VerseRef>,
 }
 
 #[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct StrongDictPage {
+    pub variants: Vec<StrongVariant>,
+    pub refs: Vec<VerseRef>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum StrongRefSort {
+    Canonical,
+    Variant,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams), into_params(parameter_in = Query))]
+pub struct StrongRefQuery {
+    /// Number of references to skip
+    pub offset: Option<usize>,
+    /// Maximum number of references to return
+    pub limit: Option<usize>,
+    /// Book range, e.g. `NT`, `John` or `Matthew-John`
+    #[cfg_attr(feature = "utoipa", param(value_type = Option<String>))]
+    pub books: Option<BookRange>,
+    /// Only references where the word is rendered like this
+    pub variant: Option<String>,
+    /// Sort order of the references
+    pub sort: Option<StrongRefSort>,
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct StrongVariant {
     pub variant: String,
     pub count: u32,
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct Book {
     pub nr: usize,
+    #[serde(skip_deserializing)]
     pub name: &'static str,
     pub chapters: Vec<Chapter>,
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct Chapter {
     pub chapter: usize,
     pub verses: Vec<Verse>,
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct Verse {
     pub verse: usize,
     pub chunks: Vec<Chunk>,
 }
 
 #[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct VerseRef {
     pub book: usize,
     pub chapter: usize,
     pub verse: usize,
-    pub chunks: Option<Vec<Chunk>>
+    pub chunks: Option<Vec<Chunk>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct Chunk {
     pub text: String,
-    pub strong: Option<RefCell<StrongNumber>>
+    pub strong: Option<StrongNumber>,
+    /// Words of Jesus, marked red in the source
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub red: bool,
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct StrongNumber {
     pub number: i32,
     pub grammar: Option<String>
 }
 
+/// A strong number together with its language, written as `G26` or `H430`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StrongKey {
+    pub greek: bool,
+    pub number: usize,
+}
+
 fn mut_find_or_insert<T: PartialEq>(vec: &mut Vec<T>, val: T) -> &mut T {
     if let Some(i) = (0..vec.len()).find(|&i| vec[i] == val) {
         &mut vec[i]
@@ -83,10 +163,19 @@ fn mut_find_or_insert<T: PartialEq>(vec: &mut Vec<T>, val: T) -> &mut T {
     }
 }
 
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// 64 bit FNV-1a hash, which unlike the std hasher is stable across releases.
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
 // Implementation
 impl Bible {
     pub fn new(identifier: &'static str, name: &'static str) -> Bible {
-        Bible { identifier: String::from(identifier), name: String::from(name), books: vec![], greek_strong_dict: HashMap::new(), hebrew_strong_dict: HashMap::new() }
+        Bible { identifier: String::from(identifier), name: String::from(name), books: vec![], greek_strong_dict: HashMap::new(), hebrew_strong_dict: HashMap::new(), versification: Versification::english(), content_hash: 0 }
     }
 
     pub fn add_book(&mut self, book: usize) {
@@ -125,7 +214,7 @@ impl Bible {
         self.get_verse(book, chapter, verse).map(|v| VerseRef::new(book, chapter, verse))
     }
 
-    pub fn insert_strong_variant(&mut self, strong_nr: i32, text: String, verse_ref: VerseRef) {
+    pub fn insert_strong_variant(&mut self, strong_nr: i32, text: String, mut verse_ref: VerseRef) {
         let entry = if verse_ref.book < 39 {
             &mut self.hebrew_strong_dict
         } else {
@@ -137,9 +226,29 @@ impl Bible {
         } else {
             entry.variants.push(StrongVariant { variant: text.to_lowercase(), count: 1});
         }
+        verse_ref.variant = Some(text.to_lowercase());
         entry.refs.push(verse_ref);
     }
 
+    /// Fills the strong dictionaries from the tagged words and detects the versification,
+    /// which the Zefania parser does while reading.
+    pub fn index(&mut self) {
+        let mut tagged = vec![];
+        for book in &self.books {
+            for chapter in &book.chapters {
+                for verse in &chapter.verses {
+                    for chunk in verse.chunks.iter().filter(|x| x.strong.is_some()) {
+                        tagged.push((chunk.strong.as_ref().unwrap().number, chunk.text.clone(), VerseRef::new(book.nr, chapter.chapter, verse.verse)));
+                    }
+                }
+            }
+        }
+        for (number, text, verse_ref) in tagged {
+            self.insert_strong_variant(number, text, verse_ref);
+        }
+        self.versification = Versification::detect(self);
+    }
+
     pub fn get_translation(&self) -> Translation {
         Translation { identifier: String::from(&self.identifier), name: String::from(&self.name) }
     }
@@ -243,11 +352,11 @@ impl Verse {
 
 impl Chunk {
     pub fn new(text: String) -> Chunk {
-        Chunk { text: text, strong: None }
+        Chunk { text, strong: None, red: false }
     }
 
     pub fn new_strong(text: String, strong_number: i32, grammar: Option<String>) -> Chunk {
-        Chunk { text: text, strong: Some(RefCell::new(StrongNumber::new(strong_number, grammar))) }
+        Chunk { text, strong: Some(StrongNumber::new(strong_number, grammar)), red: false }
     }
 
     pub fn to_string(&self) -> String {
@@ -261,16 +370,66 @@ impl StrongNumber {
     }
 }
 
+impl StrongKey {
+    pub fn new(greek: bool, number: usize) -> Self {
+        Self { greek, number }
+    }
+
+    /// Strong numbers in the old testament are hebrew, all others greek.
+    pub fn for_book(book: usize, number: i32) -> Self {
+        Self::new(book >= 39, number as usize)
+    }
+
+    pub fn get_dict_entry<'a>(&self, bible: &'a Bible) -> Option<&'a StrongDictEntry> {
+        if self.greek {
+            bible.greek_strong_dict.get(&self.number)
+        } else {
+            bible.hebrew_strong_dict.get(&self.number)
+        }
+    }
+}
+
+impl fmt::Display for StrongKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", if self.greek { "G" } else { "H" }, self.number)
+    }
+}
+
+impl FromStr for StrongKey {
+    type Err = ParseReferenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let greek = match s.chars().next() {
+            Some('G') | Some('g') => true,
+            Some('H') | Some('h') => false,
+            _ => return Err(ParseReferenceError(format!("Strong number '{}' has to start with G or H.", s))),
+        };
+        s[1..].parse::<usize>()
+            .map(|number| Self::new(greek, number))
+            .map_err(|_| ParseReferenceError(format!("Invalid strong number '{}'.", s)))
+    }
+}
+
+impl Serialize for StrongKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
 impl VerseRef {
     pub fn new(book: usize, chapter: usize, verse: usize) -> Self {
-        Self { book: book, chapter: chapter, verse: verse, chunks: None }
+        Self { book, chapter, verse, chunks: None, variant: None }
     }
 
     pub fn new_with_chunks(book: usize, chapter: usize, verse: usize, chunks: Vec<Chunk>) -> Self {
-        Self { book: book, chapter: chapter, verse: verse, chunks: Some(chunks) }
+        Self { book, chapter, verse, chunks: Some(chunks), variant: None }
     }
     pub fn to_string(&self) -> String {
-        let passage = format!("  {} {},{}", BOOKS[self.book as usize - 1], self.chapter, self.verse);
+        let passage = format!("  {}", format_reference(self.book, self.chapter, self.verse));
         if let Some(chunks) = &self.chunks {
             return format!("{}: {}", &passage, chunks.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(" "));
         }
@@ -286,8 +445,8 @@ impl StrongDictEntry {
                 let mut chunks = vec![];
                 for chunk in &v.chunks {
                     if let Some(strong) = &chunk.strong {
-                        let num = strong.borrow().number;
-                        let grammar = &strong.borrow().grammar;
+                        let num = strong.number;
+                        let grammar = &strong.grammar;
                         if let Some(grammar) = grammar {
                             chunks.push(Chunk::new_strong(String::clone(&chunk.text), num, Some(String::clone(grammar))));
                         } else {
@@ -314,6 +473,40 @@ impl StrongDictEntry {
             variants: variants
         }
     }
+
+    /// Filters and sorts the references and resolves the verse text only for
+    /// the requested page.
+    pub fn get_page(&self, bible: &Bible, query: &StrongRefQuery) -> StrongDictPage {
+        let variant = query.variant.as_ref().map(|x| x.to_lowercase());
+        let mut refs: Vec<&VerseRef> = self.refs.iter()
+            .filter(|r| query.books.iter().all(|books| books.contains(r.book)))
+            .filter(|r| variant.is_none() || r.variant == variant)
+            .collect();
+        refs.sort_by_key(|r| (r.book, r.chapter, r.verse));
+        if query.sort == Some(StrongRefSort::Variant) {
+            refs.sort_by(|a, b| a.variant.cmp(&b.variant));
+        }
+
+        let total = refs.len();
+        let offset = query.offset.unwrap_or(0);
+        let page = refs.into_iter()
+            .skip(offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .filter_map(|r| bible.get_verse(r.book, r.chapter, r.verse).map(|v| VerseRef {
+                chunks: Some(v.chunks.clone()),
+                variant: r.variant.clone(),
+                ..VerseRef::new(r.book, r.chapter, r.verse)
+            }))
+            .collect();
+
+        StrongDictPage {
+            variants: self.variants.clone(),
+            refs: page,
+            total,
+            offset,
+            limit: query.limit,
+        }
+    }
 }
 
 // impl Serialize for VerseRef {
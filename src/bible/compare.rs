@@ -0,0 +1,69 @@
+use crate::{Bible, Passage, Translation, VerseRef, format_reference};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Declarations
+#[derive(Clone, Default, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams), into_params(parameter_in = Query))]
+pub struct CompareQuery {
+    /// Comma separated identifiers of the translations, e.g. `LUTH1912,ELB1905STR` (default: all translations)
+    pub translations: Option<String>,
+}
+
+/// One verse in the english numbering together with the matching verse of each
+/// compared translation, in the order of `Comparison::translations`.
+#[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct ComparedVerse {
+    pub book: usize,
+    pub chapter: usize,
+    pub verse: usize,
+    pub reference: String,
+    /// The verses in the numbering of the translation, `null` if it lacks the verse
+    pub texts: Vec<Option<VerseRef>>,
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct Comparison {
+    pub reference: String,
+    pub translations: Vec<Translation>,
+    pub verses: Vec<ComparedVerse>,
+}
+
+// Implementation
+impl CompareQuery {
+    pub fn identifiers(&self) -> Vec<&str> {
+        self.translations.iter()
+            .flat_map(|x| x.split(','))
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .collect()
+    }
+}
+
+/// Aligns the verses of a passage, given in the english numbering, across several
+/// translations. Verses numbered differently in a translation are mapped by its versification.
+pub fn compare(bibles: &[&Bible], passage: &Passage) -> Comparison {
+    let mut verses: BTreeMap<(usize, usize, usize), Vec<Option<VerseRef>>> = BTreeMap::new();
+    for (i, bible) in bibles.iter().enumerate() {
+        for book in bible.books.iter().filter(|book| passage.from.book <= book.nr && book.nr <= passage.to.book) {
+            for (c, chapter) in book.chapters.iter().enumerate() {
+                for (v, verse) in chapter.verses.iter().enumerate() {
+                    let standard = bible.versification.to_standard(book.nr, c, v);
+                    if passage.contains(standard.0, standard.1, standard.2) {
+                        let texts = verses.entry(standard).or_insert_with(|| vec![None; bibles.len()]);
+                        texts[i] = Some(VerseRef::new_with_chunks(book.nr, c, v, verse.chunks.clone()));
+                    }
+                }
+            }
+        }
+    }
+    Comparison {
+        reference: passage.to_string(),
+        translations: bibles.iter().map(|x| x.get_translation()).collect(),
+        verses: verses.into_iter().map(|((book, chapter, verse), texts)| ComparedVerse {
+            book, chapter, verse, reference: format_reference(book, chapter, verse), texts,
+        }).collect(),
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{Bible, BookRange, StrongKey, Token, tokenize, normalize_word, format_reference};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+// Declarations
+/// What to build a concordance for: a single word, a phrase of consecutive
+/// words or all words tagged with a strong number.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Keyword {
+    Phrase(Vec<String>),
+    Strong(StrongKey),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConcordanceSort {
+    /// Canonical order of the references.
+    Reference,
+    /// Alphabetical order of the words following the keyword.
+    Following,
+}
+
+#[derive(Clone)]
+pub struct ConcordanceOptions {
+    pub context: usize,
+    pub sort: ConcordanceSort,
+    pub books: Option<BookRange>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ConcordanceLine {
+    pub book: usize,
+    pub chapter: usize,
+    pub verse: usize,
+    pub left: String,
+    pub keyword: String,
+    pub right: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ConcordanceEntry {
+    pub word: String,
+    pub lines: Vec<ConcordanceLine>,
+}
+
+pub const DEFAULT_CONTEXT: usize = 5;
+
+fn join(tokens: &[Token]) -> String {
+    tokens.iter().map(|x| x.text).collect::<Vec<&str>>().join(" ")
+}
+
+fn line(book: usize, chapter: usize, verse: usize, tokens: &[Token], start: usize, end: usize, context: usize) -> ConcordanceLine {
+    ConcordanceLine {
+        book,
+        chapter,
+        verse,
+        left: join(&tokens[start.saturating_sub(context)..start]),
+        keyword: join(&tokens[start..end]),
+        right: join(&tokens[end..(end + context).min(tokens.len())]),
+    }
+}
+
+/// Returns the end of the match starting at `start`, if the keyword matches there.
+fn match_at(keyword: &Keyword, tokens: &[Token], start: usize) -> Option<usize> {
+    match keyword {
+        Keyword::Phrase(words) => {
+            if !words.is_empty() && start + words.len() <= tokens.len()
+                && words.iter().zip(&tokens[start..]).all(|(word, token)| *word == token.word) {
+                Some(start + words.len())
+            } else {
+                None
+            }
+        },
+        Keyword::Strong(key) => {
+            if tokens[start].strong != Some(*key) || (start > 0 && tokens[start - 1].chunk == tokens[start].chunk) {
+                return None;
+            }
+            let chunk = tokens[start].chunk;
+            Some(start + tokens[start..].iter().take_while(|x| x.chunk == chunk).count())
+        },
+    }
+}
+
+fn sort_lines(lines: &mut [ConcordanceLine], sort: ConcordanceSort) {
+    if sort == ConcordanceSort::Following {
+        lines.sort_by_cached_key(|x| x.right.to_lowercase());
+    }
+}
+
+// Implementation
+impl ConcordanceLine {
+    pub fn reference(&self) -> String {
+        format_reference(self.book, self.chapter, self.verse)
+    }
+}
+
+/// Lists every occurrence of the keyword with `options.context` words of
+/// context on either side, limited to the verse the keyword occurs in.
+pub fn concordance(bible: &Bible, keyword: &Keyword, options: &ConcordanceOptions) -> Vec<ConcordanceLine> {
+    let mut lines = vec![];
+    for book in bible.books.iter().filter(|x| options.books.iter().all(|range| range.contains(x.nr))) {
+        for chapter in &book.chapters {
+            for verse in &chapter.verses {
+                let tokens = tokenize(book.nr, verse);
+                let mut i = 0;
+                while i < tokens.len() {
+                    if let Some(end) = match_at(keyword, &tokens, i) {
+                        lines.push(line(book.nr, chapter.chapter, verse.verse, &tokens, i, end, options.context));
+                        i = end;
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+        }
+    }
+    sort_lines(&mut lines, options.sort);
+    lines
+}
+
+/// Builds the concordance of all words of a translation in alphabetical order.
+/// Words occurring more than `max_count` times are left out, as printed
+/// concordances usually do for words like "and".
+pub fn full_concordance(bible: &Bible, options: &ConcordanceOptions, max_count: Option<usize>) -> Vec<ConcordanceEntry> {
+    let mut words: BTreeMap<String, Vec<ConcordanceLine>> = BTreeMap::new();
+    for book in bible.books.iter().filter(|x| options.books.iter().all(|range| range.contains(x.nr))) {
+        for chapter in &book.chapters {
+            for verse in &chapter.verses {
+                let tokens = tokenize(book.nr, verse);
+                for (i, token) in tokens.iter().enumerate() {
+                    words.entry(token.word.clone())
+                        .or_default()
+                        .push(line(book.nr, chapter.chapter, verse.verse, &tokens, i, i + 1, options.context));
+                }
+            }
+        }
+    }
+    words.into_iter()
+        .filter(|(_, lines)| max_count.iter().all(|&max| lines.len() <= max))
+        .map(|(word, mut lines)| {
+            sort_lines(&mut lines, options.sort);
+            ConcordanceEntry { word, lines }
+        })
+        .collect()
+}
+
+impl Default for ConcordanceOptions {
+    fn default() -> Self {
+        Self { context: DEFAULT_CONTEXT, sort: ConcordanceSort::Reference, books: None }
+    }
+}
+
+impl FromStr for Keyword {
+    type Err = String;
+
+    /// Parses a strong number (`G26`, `H430`) or a word or phrase.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(key) = StrongKey::from_str(s) {
+            return Ok(Keyword::Strong(key));
+        }
+        let words: Vec<String> = s.split_whitespace().map(normalize_word).filter(|x| !x.is_empty()).collect();
+        if words.is_empty() {
+            Err(format!("Invalid keyword '{}'.", s))
+        } else {
+            Ok(Keyword::Phrase(words))
+        }
+    }
+}
+
+impl FromStr for ConcordanceSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "reference" => Ok(Self::Reference),
+            "following" => Ok(Self::Following),
+            _ => Err(format!("Unknown sort order '{}'.", s)),
+        }
+    }
+}
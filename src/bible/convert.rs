@@ -0,0 +1,149 @@
+use crate::{Bible, BibleExporter, BibleParser, JsonBible, OsisBible, TextBible, UsfmBible, ZefaniaBible};
+#[cfg(feature = "sqlite")]
+use crate::SqliteBible;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+
+// Declarations
+/// What could not be carried over, counted by kind, e.g. `notes: 12`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Losses(BTreeMap<&'static str, usize>);
+
+type ParseFn = fn(&str, &mut Losses) -> Result<Bible, Box<dyn Error>>;
+type WriteFn = fn(&Bible, &str, &mut Losses) -> Result<(), Box<dyn Error>>;
+
+/// A format bibles can be read from, written to or both. New formats are supported
+/// by implementing `BibleParser` and `BibleExporter` and adding them to `FORMATS`.
+pub struct BibleFormat {
+    pub name: &'static str,
+    /// Whether the file (or the directory for formats of several files) is in this
+    /// format, given its path and first bytes
+    detect: fn(&Path, &[u8]) -> bool,
+    parse: Option<ParseFn>,
+    write: Option<WriteFn>,
+}
+
+/// The result of a conversion, with what the reader and the writer left out.
+#[derive(Debug, Serialize)]
+pub struct Conversion {
+    pub identifier: String,
+    pub books: usize,
+    pub verses: usize,
+    /// Content of the input the bible cannot hold
+    pub unread: Losses,
+    /// Content of the bible the output format cannot represent
+    pub unwritten: Losses,
+}
+
+/// The supported formats, in the order they are detected.
+pub static FORMATS: &[BibleFormat] = &[
+    BibleFormat {
+        name: "zefania",
+        detect: |_, head| contains(head, "<XMLBIBLE"),
+        parse: Some(<ZefaniaBible as BibleParser>::parse_with_losses),
+        write: Some(<ZefaniaBible as BibleExporter>::write),
+    },
+    BibleFormat {
+        name: "osis",
+        detect: |_, head| contains(head, "<osis"),
+        parse: Some(<OsisBible as BibleParser>::parse_with_losses),
+        write: Some(<OsisBible as BibleExporter>::write),
+    },
+    BibleFormat {
+        name: "usfm",
+        detect: |path, head| path.is_dir() || head.starts_with(b"\\id ") || contains(head, "\n\\id "),
+        parse: Some(<UsfmBible as BibleParser>::parse_with_losses),
+        write: Some(<UsfmBible as BibleExporter>::write),
+    },
+    BibleFormat {
+        name: "json",
+        detect: |_, head| head.iter().find(|x| !x.is_ascii_whitespace()) == Some(&b'{'),
+        parse: Some(<JsonBible as BibleParser>::parse_with_losses),
+        write: Some(<JsonBible as BibleExporter>::write),
+    },
+    #[cfg(feature = "sqlite")]
+    BibleFormat {
+        name: "sqlite",
+        detect: |_, head| head.starts_with(b"SQLite format 3\0"),
+        parse: Some(<SqliteBible as BibleParser>::parse_with_losses),
+        write: Some(<SqliteBible as BibleExporter>::write),
+    },
+    BibleFormat {
+        name: "text",
+        detect: |_, _| false,
+        parse: None,
+        write: Some(<TextBible as BibleExporter>::write),
+    },
+];
+
+// Implementation
+fn contains(head: &[u8], text: &str) -> bool {
+    head.windows(text.len()).any(|x| x == text.as_bytes())
+}
+
+impl Losses {
+    pub fn add(&mut self, kind: &'static str, count: usize) {
+        if count > 0 {
+            *self.0.entry(kind).or_insert(0) += count;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        self.0.iter().map(|(kind, count)| (*kind, *count))
+    }
+}
+
+impl fmt::Display for Losses {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let losses: Vec<String> = self.iter().map(|(kind, count)| format!("{} {}", count, kind)).collect();
+        write!(f, "{}", losses.join(", "))
+    }
+}
+
+impl BibleFormat {
+    pub fn can_read(&self) -> bool {
+        self.parse.is_some()
+    }
+
+    pub fn can_write(&self) -> bool {
+        self.write.is_some()
+    }
+}
+
+pub fn find_format(name: &str) -> Option<&'static BibleFormat> {
+    FORMATS.iter().find(|x| x.name.eq_ignore_ascii_case(name))
+}
+
+/// Detects the format of a file by its first bytes, or of a directory of usfm files.
+pub fn detect_format(path: &str) -> Option<&'static BibleFormat> {
+    let path = Path::new(path);
+    let mut head = vec![];
+    if path.is_file() {
+        std::fs::File::open(path).ok()?.take(4096).read_to_end(&mut head).ok()?;
+    }
+    FORMATS.iter().find(|x| x.can_read() && (x.detect)(path, &head))
+}
+
+/// Reads the bible at `input` in the format `from` and writes it to `output` in the format `to`.
+pub fn convert(input: &str, from: &BibleFormat, output: &str, to: &BibleFormat) -> Result<Conversion, Box<dyn Error>> {
+    let parse = from.parse.ok_or_else(|| format!("Bibles cannot be read from {}.", from.name))?;
+    let write = to.write.ok_or_else(|| format!("Bibles cannot be written as {}.", to.name))?;
+    let (mut unread, mut unwritten) = (Losses::default(), Losses::default());
+    let bible = parse(input, &mut unread)?;
+    write(&bible, output, &mut unwritten)?;
+    Ok(Conversion {
+        identifier: bible.identifier.clone(),
+        books: bible.books.len(),
+        verses: bible.books.iter().flat_map(|x| x.chapters.iter()).map(|x| x.verses.len()).sum(),
+        unread,
+        unwritten,
+    })
+}
@@ -0,0 +1,264 @@
+use crate::{Bible, Chapter, StrongKey};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// Declarations
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum CooccurrenceScope {
+    /// Two strong numbers co-occur if they are tagged in the same verse.
+    Verse,
+    /// Two strong numbers co-occur if they are within a sliding window of
+    /// tagged words inside a chapter.
+    Window,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub enum AssociationMeasure {
+    #[serde(rename = "pmi")]
+    Pmi,
+    #[serde(rename = "llr")]
+    LogLikelihood,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams), into_params(parameter_in = Query))]
+pub struct RelatedQuery {
+    /// Co-occurrence scope (default: verse)
+    pub scope: Option<CooccurrenceScope>,
+    /// Window size in tagged words for the window scope (default: 5, at most 50)
+    pub window: Option<usize>,
+    /// Association measure (default: llr)
+    pub measure: Option<AssociationMeasure>,
+    /// Minimum number of co-occurrences (default: 2)
+    pub min_count: Option<u64>,
+    /// Maximum number of related strong numbers (default: 20)
+    pub limit: Option<usize>,
+}
+
+/// Sparse co-occurrence counts over strong numbers. `units` is the number of
+/// verses or windows, `frequencies` counts the units a strong number occurs in
+/// and `pairs` the units two strong numbers share.
+pub struct CooccurrenceMatrix {
+    pub units: u64,
+    pub frequencies: HashMap<StrongKey, u64>,
+    pub pairs: HashMap<(StrongKey, StrongKey), u64>,
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct Neighbour {
+    #[cfg_attr(feature = "utoipa", schema(value_type = String, example = "G4102"))]
+    pub strong: StrongKey,
+    pub variant: Option<String>,
+    pub count: u64,
+    pub frequency: u64,
+    pub score: f64,
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct Related {
+    #[cfg_attr(feature = "utoipa", schema(value_type = String, example = "G4102"))]
+    pub strong: StrongKey,
+    pub scope: CooccurrenceScope,
+    pub window: Option<usize>,
+    pub measure: AssociationMeasure,
+    pub frequency: u64,
+    pub units: u64,
+    pub neighbours: Vec<Neighbour>,
+}
+
+pub const DEFAULT_WINDOW: usize = 5;
+/// Larger windows cost time proportional to their size for every tagged word.
+pub const MAX_WINDOW: usize = 50;
+pub const DEFAULT_MIN_COUNT: u64 = 2;
+pub const DEFAULT_RELATED_LIMIT: usize = 20;
+
+fn ordered(a: StrongKey, b: StrongKey) -> (StrongKey, StrongKey) {
+    if a <= b { (a, b) } else { (b, a) }
+}
+
+fn chapter_keys(book: usize, chapter: &Chapter) -> Vec<Vec<StrongKey>> {
+    chapter.verses.iter().map(|verse| {
+        verse.chunks.iter()
+            .filter_map(|chunk| chunk.strong.as_ref().map(|strong| StrongKey::for_book(book, strong.number)))
+            .collect()
+    }).collect()
+}
+
+// Implementation
+impl CooccurrenceMatrix {
+    /// Builds the full matrix over all pairs of strong numbers.
+    pub fn build(bible: &Bible, scope: CooccurrenceScope, window: usize) -> Self {
+        Self::build_matrix(bible, scope, window, None)
+    }
+
+    /// Builds the matrix with the pair counts restricted to pairs containing
+    /// `focus`, which is much cheaper if only one word is of interest.
+    pub fn build_for(bible: &Bible, scope: CooccurrenceScope, window: usize, focus: StrongKey) -> Self {
+        Self::build_matrix(bible, scope, window, Some(focus))
+    }
+
+    fn build_matrix(bible: &Bible, scope: CooccurrenceScope, window: usize, focus: Option<StrongKey>) -> Self {
+        let mut matrix = Self { units: 0, frequencies: HashMap::new(), pairs: HashMap::new() };
+        for book in &bible.books {
+            for chapter in &book.chapters {
+                let verses = chapter_keys(book.nr, chapter);
+                match scope {
+                    CooccurrenceScope::Verse => {
+                        for keys in verses {
+                            matrix.add_unit(keys, focus);
+                        }
+                    },
+                    CooccurrenceScope::Window => {
+                        let keys: Vec<StrongKey> = verses.into_iter().flatten().collect();
+                        let window = window.max(2);
+                        if keys.len() <= window {
+                            matrix.add_unit(keys, focus);
+                        } else {
+                            for w in keys.windows(window) {
+                                matrix.add_unit(w.to_vec(), focus);
+                            }
+                        }
+                    },
+                }
+            }
+        }
+        matrix
+    }
+
+    fn add_unit(&mut self, mut keys: Vec<StrongKey>, focus: Option<StrongKey>) {
+        if keys.is_empty() {
+            return;
+        }
+        keys.sort();
+        keys.dedup();
+        self.units += 1;
+        for key in &keys {
+            *self.frequencies.entry(*key).or_insert(0) += 1;
+        }
+        match focus {
+            Some(focus) => {
+                if keys.contains(&focus) {
+                    for key in keys.iter().filter(|&&x| x != focus) {
+                        *self.pairs.entry(ordered(focus, *key)).or_insert(0) += 1;
+                    }
+                }
+            },
+            None => {
+                for i in 0..keys.len() {
+                    for j in i + 1..keys.len() {
+                        *self.pairs.entry((keys[i], keys[j])).or_insert(0) += 1;
+                    }
+                }
+            },
+        }
+    }
+
+    pub fn frequency(&self, key: StrongKey) -> u64 {
+        self.frequencies.get(&key).copied().unwrap_or(0)
+    }
+
+    pub fn count(&self, a: StrongKey, b: StrongKey) -> u64 {
+        self.pairs.get(&ordered(a, b)).copied().unwrap_or(0)
+    }
+
+    /// Association score of two strong numbers. Negative log-likelihood scores
+    /// mark pairs that occur less often together than expected.
+    pub fn score(&self, a: StrongKey, b: StrongKey, measure: AssociationMeasure) -> f64 {
+        let n = self.units as f64;
+        let (ab, fa, fb) = (self.count(a, b) as f64, self.frequency(a) as f64, self.frequency(b) as f64);
+        if ab == 0.0 || n == 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        match measure {
+            AssociationMeasure::Pmi => (ab * n / (fa * fb)).log2(),
+            AssociationMeasure::LogLikelihood => {
+                let cells = [
+                    (ab, fa, fb),
+                    (fa - ab, fa, n - fb),
+                    (fb - ab, n - fa, fb),
+                    (n - fa - fb + ab, n - fa, n - fb),
+                ];
+                let g2 = 2.0 * cells.iter()
+                    .filter(|(k, _, _)| *k > 0.0)
+                    .map(|(k, row, col)| k * (k * n / (row * col)).ln())
+                    .sum::<f64>();
+                if ab * n < fa * fb { -g2 } else { g2 }
+            },
+        }
+    }
+
+    /// Ranks all strong numbers co-occurring with `key` at least `min_count` times.
+    pub fn neighbours(&self, key: StrongKey, measure: AssociationMeasure, min_count: u64) -> Vec<Neighbour> {
+        let mut res: Vec<Neighbour> = self.pairs.iter()
+            .filter(|((a, b), &count)| (*a == key || *b == key) && count >= min_count)
+            .map(|((a, b), &count)| {
+                let other = if *a == key { *b } else { *a };
+                Neighbour {
+                    strong: other,
+                    variant: None,
+                    count,
+                    frequency: self.frequency(other),
+                    score: self.score(key, other, measure),
+                }
+            })
+            .collect();
+        res.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal).then(b.count.cmp(&a.count)).then(a.strong.cmp(&b.strong)));
+        res
+    }
+}
+
+/// Finds the strong numbers most strongly associated with `key`, together with
+/// their most frequent rendering in the given bible.
+pub fn related(bible: &Bible, key: StrongKey, query: &RelatedQuery) -> Related {
+    let scope = query.scope.unwrap_or(CooccurrenceScope::Verse);
+    let window = query.window.unwrap_or(DEFAULT_WINDOW);
+    let measure = query.measure.unwrap_or(AssociationMeasure::LogLikelihood);
+    let matrix = CooccurrenceMatrix::build_for(bible, scope, window, key);
+    let mut neighbours = matrix.neighbours(key, measure, query.min_count.unwrap_or(DEFAULT_MIN_COUNT));
+    neighbours.truncate(query.limit.unwrap_or(DEFAULT_RELATED_LIMIT));
+    for neighbour in neighbours.iter_mut() {
+        neighbour.variant = neighbour.strong.get_dict_entry(bible)
+            .and_then(|entry| entry.variants.iter().max_by_key(|x| x.count))
+            .map(|x| x.variant.clone());
+    }
+    Related {
+        strong: key,
+        scope,
+        window: if scope == CooccurrenceScope::Window { Some(window) } else { None },
+        measure,
+        frequency: matrix.frequency(key),
+        units: matrix.units,
+        neighbours,
+    }
+}
+
+impl FromStr for CooccurrenceScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "verse" => Ok(Self::Verse),
+            "window" => Ok(Self::Window),
+            _ => Err(format!("Unknown scope '{}'.", s)),
+        }
+    }
+}
+
+impl FromStr for AssociationMeasure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pmi" => Ok(Self::Pmi),
+            "llr" => Ok(Self::LogLikelihood),
+            _ => Err(format!("Unknown measure '{}'.", s)),
+        }
+    }
+}
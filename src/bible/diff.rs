@@ -0,0 +1,221 @@
+use crate::{Bible, BookRange, StrongKey, Translation, Verse, format_reference};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// Declarations
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// A run of words which is unchanged, only in the new or only in the old verse.
+#[derive(Clone, Debug, Serialize)]
+pub struct DiffSegment {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// A word whose strong number or grammar changed while the text stayed the same.
+#[derive(Clone, Debug, Serialize)]
+pub struct TagChange {
+    pub word: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum VerseChange {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct VerseDiff {
+    pub book: usize,
+    pub chapter: usize,
+    pub verse: usize,
+    pub reference: String,
+    pub change: VerseChange,
+    pub old_text: Option<String>,
+    pub new_text: Option<String>,
+    pub words: Vec<DiffSegment>,
+    pub tags: Vec<TagChange>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct BibleDiff {
+    pub old: Translation,
+    pub new: Translation,
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+    pub unchanged: usize,
+    pub verses: Vec<VerseDiff>,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct DiffOptions {
+    /// Only compare these books
+    pub books: Option<BookRange>,
+    /// Compare the wording only, e.g. of two different translations
+    pub ignore_tags: bool,
+}
+
+/// A word of a verse together with the strong number and grammar of its chunk.
+struct TaggedWord<'a> {
+    text: &'a str,
+    tag: Option<String>,
+}
+
+/// The old and the new version of a verse.
+type VersePair<'a> = (Option<&'a Verse>, Option<&'a Verse>);
+
+// Implementation
+fn tagged_words(book: usize, verse: &Verse) -> Vec<TaggedWord<'_>> {
+    let mut words = vec![];
+    for chunk in &verse.chunks {
+        let tag = chunk.strong.as_ref().map(|strong| {
+            let key = StrongKey::for_book(book, strong.number);
+            match &strong.grammar {
+                Some(grammar) => format!("{} {}", key, grammar),
+                None => key.to_string(),
+            }
+        });
+        words.extend(chunk.text.split_whitespace().map(|text| TaggedWord { text, tag: tag.clone() }));
+    }
+    words
+}
+
+/// Longest common subsequence of two word lists as pairs of indices.
+fn common_words<T: PartialEq>(a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j, mut res) = (0, 0, vec![]);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            res.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    res
+}
+
+fn push_segment(segments: &mut Vec<DiffSegment>, op: DiffOp, text: &str) {
+    match segments.last_mut() {
+        Some(last) if last.op == op => {
+            last.text.push(' ');
+            last.text.push_str(text);
+        },
+        _ => segments.push(DiffSegment { op, text: String::from(text) }),
+    }
+}
+
+/// Word level diff of two texts, consecutive words with the same operation are joined.
+pub fn diff_words(old: &[&str], new: &[&str]) -> Vec<DiffSegment> {
+    let mut segments = vec![];
+    let (mut i, mut j) = (0, 0);
+    for (ci, cj) in common_words(old, new).into_iter().chain(std::iter::once((old.len(), new.len()))) {
+        for text in &old[i..ci] {
+            push_segment(&mut segments, DiffOp::Delete, text);
+        }
+        for text in &new[j..cj] {
+            push_segment(&mut segments, DiffOp::Insert, text);
+        }
+        if ci < old.len() {
+            push_segment(&mut segments, DiffOp::Equal, old[ci]);
+        }
+        i = ci + 1;
+        j = cj + 1;
+    }
+    segments
+}
+
+fn verse_text(verse: &Verse) -> String {
+    verse.chunks.iter().map(|x| x.text.as_str()).collect::<Vec<&str>>().join(" ")
+}
+
+/// Compares two versions of a verse, `None` if text and tags are the same.
+pub fn diff_verse(book: usize, old: &Verse, new: &Verse, options: &DiffOptions) -> Option<(Vec<DiffSegment>, Vec<TagChange>)> {
+    let (old_words, new_words) = (tagged_words(book, old), tagged_words(book, new));
+    let old_texts: Vec<&str> = old_words.iter().map(|x| x.text).collect();
+    let new_texts: Vec<&str> = new_words.iter().map(|x| x.text).collect();
+    let mut tags = vec![];
+    if !options.ignore_tags {
+        for (i, j) in common_words(&old_texts, &new_texts) {
+            if old_words[i].tag != new_words[j].tag {
+                tags.push(TagChange { word: String::from(old_words[i].text), old: old_words[i].tag.clone(), new: new_words[j].tag.clone() });
+            }
+        }
+    }
+    if old_texts == new_texts && tags.is_empty() {
+        return None;
+    }
+    Some((diff_words(&old_texts, &new_texts), tags))
+}
+
+/// Compares two bibles verse by verse. Verses are aligned by the english numbering,
+/// so two translations with different versifications can be compared as well.
+pub fn diff_bibles(old: &Bible, new: &Bible, options: &DiffOptions) -> BibleDiff {
+    let mut verses: BTreeMap<(usize, usize, usize), VersePair<'_>> = BTreeMap::new();
+    for (i, bible) in [old, new].iter().enumerate() {
+        for book in bible.books.iter().filter(|x| options.books.iter().all(|range| range.contains(x.nr))) {
+            for (c, chapter) in book.chapters.iter().enumerate() {
+                for (v, verse) in chapter.verses.iter().enumerate() {
+                    let entry = verses.entry(bible.versification.to_standard(book.nr, c, v)).or_insert((None, None));
+                    if i == 0 { entry.0 = Some(verse) } else { entry.1 = Some(verse) }
+                }
+            }
+        }
+    }
+
+    let mut diff = BibleDiff {
+        old: old.get_translation(), new: new.get_translation(), added: 0, removed: 0, changed: 0, unchanged: 0, verses: vec![],
+    };
+    for ((book, chapter, verse), pair) in verses {
+        let (change, words, tags) = match pair {
+            (Some(a), Some(b)) => match diff_verse(book, a, b, options) {
+                Some((words, tags)) => (VerseChange::Changed, words, tags),
+                None => {
+                    diff.unchanged += 1;
+                    continue;
+                },
+            },
+            (None, Some(b)) => (VerseChange::Added, vec![DiffSegment { op: DiffOp::Insert, text: verse_text(b) }], vec![]),
+            (Some(a), None) => (VerseChange::Removed, vec![DiffSegment { op: DiffOp::Delete, text: verse_text(a) }], vec![]),
+            (None, None) => continue,
+        };
+        match change {
+            VerseChange::Added => diff.added += 1,
+            VerseChange::Removed => diff.removed += 1,
+            VerseChange::Changed => diff.changed += 1,
+        }
+        diff.verses.push(VerseDiff {
+            book, chapter, verse,
+            reference: format_reference(book, chapter, verse),
+            change,
+            old_text: pair.0.map(verse_text),
+            new_text: pair.1.map(verse_text),
+            words,
+            tags,
+        });
+    }
+    diff
+}
@@ -0,0 +1,49 @@
+use crate::{Bible, Book, Losses, BOOKS, content_hash};
+use crate::{BibleExporter, BibleParser};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+// Declarations
+/// Reads and writes a bible as one json document with its books, chapters and
+/// verses like the REST api answers them.
+pub struct JsonBible;
+
+#[derive(Deserialize)]
+struct Document {
+    identifier: String,
+    name: String,
+    books: Vec<Book>,
+}
+
+#[derive(Serialize)]
+struct DocumentRef<'a> {
+    identifier: &'a str,
+    name: &'a str,
+    books: &'a [Book],
+}
+
+// Implementation
+impl BibleParser for JsonBible {
+    fn parse(path: &str) -> Result<Bible, Box<dyn Error>> {
+        let text = std::fs::read(path)?;
+        let document: Document = serde_json::from_slice(&text)?;
+        if let Some(book) = document.books.iter().find(|x| x.nr >= BOOKS.len()) {
+            return Err(From::from(format!("{}: invalid book number {}, there are {} books.", path, book.nr, BOOKS.len())));
+        }
+        let mut bible = Bible::new("Unknown", "Unknown translation");
+        bible.identifier = document.identifier;
+        bible.name = document.name;
+        bible.books = document.books;
+        bible.index();
+        bible.content_hash = content_hash(&text);
+        Ok(bible)
+    }
+}
+
+impl BibleExporter for JsonBible {
+    fn write(bible: &Bible, path: &str, _losses: &mut Losses) -> Result<(), Box<dyn Error>> {
+        let document = DocumentRef { identifier: &bible.identifier, name: &bible.name, books: &bible.books };
+        std::fs::write(path, serde_json::to_string(&document)?)?;
+        Ok(())
+    }
+}
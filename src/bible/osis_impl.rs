@@ -0,0 +1,183 @@
+use crate::{Bible, Chunk, Losses, StrongKey, StrongNumber, Verse, content_hash, escape};
+use crate::{BibleExporter, BibleParser};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use std::error::Error;
+use std::fmt::Write;
+
+// Declarations
+/// Reads and writes OSIS XML. Verses may be containers or milestones, words of Jesus
+/// are `q` elements with `who="Jesus"`. Notes and headings are left out when reading.
+pub struct OsisBible;
+
+/// The OSIS book identifiers in the order of `BOOKS`.
+static OSIS_BOOKS: &[&str] = &[
+    "Gen", "Exod", "Lev", "Num", "Deut", "Josh", "Judg", "Ruth", "1Sam", "2Sam", "1Kgs", "2Kgs", "1Chr", "2Chr", "Ezra", "Neh", "Esth",
+    "Job", "Ps", "Prov", "Eccl", "Song", "Isa", "Jer", "Lam", "Ezek", "Dan", "Hos", "Joel", "Amos", "Obad", "Jonah", "Mic", "Nah",
+    "Hab", "Zeph", "Hag", "Zech", "Mal", "Matt", "Mark", "Luke", "John", "Acts", "Rom", "1Cor", "2Cor", "Gal", "Eph", "Phil", "Col",
+    "1Thess", "2Thess", "1Tim", "2Tim", "Titus", "Phlm", "Heb", "Jas", "1Pet", "2Pet", "1John", "2John", "3John", "Jude", "Rev",
+];
+
+/// Elements whose content is no verse text.
+static SKIPPED: &[(&[u8], &str)] = &[(b"note", "notes"), (b"title", "headings")];
+
+// Implementation
+fn attribute(e: &BytesStart, reader: &Reader<&[u8]>, name: &[u8]) -> Option<String> {
+    e.attributes().flatten()
+        .find(|x| x.key == name)
+        .and_then(|x| x.unescape_and_decode_value(reader).ok())
+}
+
+/// The 0-based book, chapter and verse of an osisID like `John.3.16`, the first of several.
+fn parse_osis_id(id: &str) -> Option<(usize, usize, usize)> {
+    let mut parts = id.split_whitespace().next()?.split('.');
+    let book = parts.next()?;
+    let book = OSIS_BOOKS.iter().position(|x| *x == book)?;
+    let chapter = parts.next()?.parse::<usize>().ok()?.checked_sub(1)?;
+    let verse = parts.next()?.parse::<usize>().ok()?.checked_sub(1)?;
+    Some((book, chapter, verse))
+}
+
+/// The first strong number of a `lemma` attribute, e.g. `strong:G25 lemma.TR:agapao`.
+fn parse_lemma(lemma: &str) -> Option<i32> {
+    lemma.split_whitespace()
+        .filter_map(|x| x.strip_prefix("strong:"))
+        .find_map(|x| x.trim_start_matches(&['G', 'H'][..]).parse().ok())
+}
+
+impl BibleParser for OsisBible {
+    fn parse(path: &str) -> Result<Bible, Box<dyn Error>> {
+        Self::parse_with_losses(path, &mut Losses::default())
+    }
+
+    fn parse_with_losses(path: &str, losses: &mut Losses) -> Result<Bible, Box<dyn Error>> {
+        let text = std::fs::read(path)?;
+        let mut reader = Reader::from_reader(text.as_slice());
+        let mut bible = Bible::new("Unknown", "Unknown translation");
+        let mut verse: Option<(usize, usize, usize)> = None;
+        // the strong number and grammar of the open `w` element
+        let mut word: Option<StrongNumber> = None;
+        let (mut red, mut work_title, mut skipped) = (0, false, 0);
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf)? {
+                Event::Eof => break,
+                Event::Start(ref e) if skipped > 0 && SKIPPED.iter().any(|(name, _)| *name == e.name()) => skipped += 1,
+                Event::End(ref e) if skipped > 0 && SKIPPED.iter().any(|(name, _)| *name == e.name()) => skipped -= 1,
+                _ if skipped > 0 => {},
+                Event::Start(ref e) => match e.name() {
+                    b"osisText" => if let Some(id) = attribute(e, &reader, b"osisIDWork") { bible.identifier = id },
+                    b"title" if bible.books.is_empty() && bible.name == "Unknown translation" => work_title = true,
+                    b"verse" => if let Some(id) = attribute(e, &reader, b"osisID").and_then(|x| parse_osis_id(&x)) {
+                        verse = Some(id);
+                        bible.get_chapter_mut(id.0, id.1).add_verse(Verse::new(id.2));
+                    },
+                    b"w" => word = attribute(e, &reader, b"lemma").and_then(|x| parse_lemma(&x))
+                        .map(|number| StrongNumber::new(number, attribute(e, &reader, b"morph")
+                            .map(|x| String::from(x.split_once(':').map(|x| x.1).unwrap_or(&x))))),
+                    b"q" if attribute(e, &reader, b"who").as_deref() == Some("Jesus") => red += 1,
+                    b"q" => red += (red > 0) as usize,
+                    name => if let Some((_, kind)) = SKIPPED.iter().find(|(x, _)| *x == name) {
+                        losses.add(kind, 1);
+                        skipped = 1;
+                    },
+                },
+                Event::Empty(ref e) => match e.name() {
+                    // milestones mark the start and end of verses and quotes
+                    b"verse" if attribute(e, &reader, b"eID").is_some() => verse = None,
+                    b"verse" => if let Some(id) = attribute(e, &reader, b"osisID").and_then(|x| parse_osis_id(&x)) {
+                        verse = Some(id);
+                        bible.get_chapter_mut(id.0, id.1).add_verse(Verse::new(id.2));
+                    },
+                    b"q" if attribute(e, &reader, b"who").as_deref() == Some("Jesus") && attribute(e, &reader, b"sID").is_some() => red += 1,
+                    b"q" if attribute(e, &reader, b"eID").is_some() && red > 0 => red -= 1,
+                    b"note" => losses.add("notes", 1),
+                    _ => {},
+                },
+                Event::End(ref e) => match e.name() {
+                    b"verse" => verse = None,
+                    b"w" => word = None,
+                    b"q" if red > 0 => red -= 1,
+                    b"title" => work_title = false,
+                    _ => {},
+                },
+                Event::Text(e) => {
+                    let text = e.unescape_and_decode(&reader)?;
+                    let text = text.trim();
+                    if work_title && !text.is_empty() {
+                        bible.name = String::from(text);
+                        work_title = false;
+                    }
+                    if let (Some((b, c, v)), false) = (verse, text.is_empty()) {
+                        let chunk = match &word {
+                            Some(strong) => Chunk::new_strong(String::from(text), strong.number, strong.grammar.clone()),
+                            None => Chunk::new(String::from(text)),
+                        };
+                        bible.get_verse_mut(b, c, v).add_strong(Chunk { red: red > 0, ..chunk });
+                    }
+                },
+                _ => {},
+            }
+            buf.clear();
+        }
+        if bible.identifier == "Unknown" {
+            return Err(From::from(format!("{} has no osisText element with an osisIDWork.", path)));
+        }
+        bible.index();
+        bible.content_hash = content_hash(&text);
+        Ok(bible)
+    }
+}
+
+impl BibleExporter for OsisBible {
+    fn write(bible: &Bible, path: &str, _losses: &mut Losses) -> Result<(), Box<dyn Error>> {
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str("<osis xmlns=\"http://www.bibletechnologies.net/2003/OSIS/namespace\">\n");
+        let _ = writeln!(out, "<osisText osisIDWork=\"{}\" osisRefWork=\"Bible\">", escape(&bible.identifier));
+        let _ = writeln!(out, "<header>\n<work osisWork=\"{}\">\n<title>{}</title>\n<refSystem>Bible</refSystem>\n</work>\n</header>",
+            escape(&bible.identifier), escape(&bible.name));
+        for book in bible.books.iter().filter(|x| x.nr < OSIS_BOOKS.len()) {
+            let id = OSIS_BOOKS[book.nr];
+            let _ = writeln!(out, "<div type=\"book\" osisID=\"{}\">", id);
+            for chapter in &book.chapters {
+                let _ = writeln!(out, "<chapter osisID=\"{}.{}\">", id, chapter.chapter + 1);
+                for verse in &chapter.verses {
+                    let _ = write!(out, "<verse osisID=\"{0}.{1}.{2}\">", id, chapter.chapter + 1, verse.verse + 1);
+                    let mut red = false;
+                    for (i, chunk) in verse.chunks.iter().enumerate() {
+                        if chunk.red != red && !chunk.red {
+                            out.push_str("</q>");
+                        }
+                        if i > 0 {
+                            out.push(' ');
+                        }
+                        if chunk.red != red && chunk.red {
+                            out.push_str("<q who=\"Jesus\" marker=\"\">");
+                        }
+                        red = chunk.red;
+                        match &chunk.strong {
+                            Some(strong) => {
+                                let _ = write!(out, "<w lemma=\"strong:{}\"", StrongKey::for_book(book.nr, strong.number));
+                                if let Some(grammar) = &strong.grammar {
+                                    let prefix = if book.nr >= 39 { "robinson" } else { "x-morph" };
+                                    let _ = write!(out, " morph=\"{}:{}\"", prefix, escape(grammar));
+                                }
+                                let _ = write!(out, ">{}</w>", escape(&chunk.text));
+                            },
+                            None => out.push_str(&escape(&chunk.text)),
+                        }
+                    }
+                    if red {
+                        out.push_str("</q>");
+                    }
+                    out.push_str("</verse>\n");
+                }
+                out.push_str("</chapter>\n");
+            }
+            out.push_str("</div>\n");
+        }
+        out.push_str("</osisText>\n</osis>\n");
+        std::fs::write(path, out)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{Bible, Book, Chapter, ParseReferenceError, BOOKS, find_book, format_reference};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// Declarations
+/// A position in the `Bible` tree, 0-based. A missing chapter or verse stands for
+/// the first one at the start of a passage and for the last one at its end.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct PassagePoint {
+    pub book: usize,
+    pub chapter: Option<usize>,
+    pub verse: Option<usize>,
+}
+
+/// An inclusive range of verses, which may cross chapter and book boundaries.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct Passage {
+    pub from: PassagePoint,
+    pub to: PassagePoint,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct PassageQuery {
+    /// Passages as reference string, separated by `;`, e.g. `Matthew 5:1-7:29; John 3,16`
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+    /// Passages with 0-based book, chapter and verse numbers
+    pub passages: Option<Vec<Passage>>,
+}
+
+/// The verses of one passage, grouped by book and chapter.
+#[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct PassageText {
+    pub reference: String,
+    pub books: Vec<Book>,
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct PassageResult {
+    pub verse_count: usize,
+    pub passages: Vec<PassageText>,
+}
+
+/// Upper bound of chapter and verse numbers, above the longest chapter of any book.
+const MAX_NUMBER: usize = 1000;
+
+// Implementation
+fn parse_number(s: &str, what: &str) -> Result<usize, ParseReferenceError> {
+    match s.trim().parse::<usize>() {
+        Ok(nr) if nr > 0 => Ok(nr - 1),
+        _ => Err(ParseReferenceError(format!("Invalid {} number '{}'.", what, s.trim()))),
+    }
+}
+
+/// Parses `3`, `3:16` or `3,16` into a 0-based chapter and verse.
+fn parse_chapter_verse(s: &str) -> Result<(Option<usize>, Option<usize>), ParseReferenceError> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok((None, None));
+    }
+    match s.split_once(&[':', ',', '.'][..]) {
+        Some((chapter, verse)) => Ok((Some(parse_number(chapter, "chapter")?), Some(parse_number(verse, "verse")?))),
+        None => Ok((Some(parse_number(s, "chapter")?), None)),
+    }
+}
+
+/// Splits `1 John 3:16` into the book name and the chapter and verse part.
+fn split_book(s: &str) -> Option<(&str, &str)> {
+    let end = s.char_indices().rfind(|(_, c)| c.is_alphabetic()).map(|(i, c)| i + c.len_utf8())?;
+    Some((&s[..end], &s[end..]))
+}
+
+fn parse_point(s: &str) -> Result<PassagePoint, ParseReferenceError> {
+    let (name, rest) = split_book(s.trim()).ok_or_else(|| ParseReferenceError(format!("Missing book in '{}'.", s.trim())))?;
+    let book = find_book(name).ok_or_else(|| ParseReferenceError(format!("Unknown book '{}'.", name.trim())))?;
+    let (chapter, verse) = parse_chapter_verse(rest)?;
+    Ok(PassagePoint { book, chapter, verse })
+}
+
+impl PassagePoint {
+    fn start(&self) -> (usize, usize, usize) {
+        (self.book, self.chapter.unwrap_or(0), self.verse.unwrap_or(0))
+    }
+
+    fn end(&self) -> (usize, usize, usize) {
+        (self.book, self.chapter.unwrap_or(usize::MAX), self.verse.unwrap_or(usize::MAX))
+    }
+}
+
+impl Passage {
+    pub fn new(from: PassagePoint, to: PassagePoint) -> Self {
+        Self { from, to }
+    }
+
+    pub fn validate(&self) -> Result<(), ParseReferenceError> {
+        if self.from.book >= BOOKS.len() || self.to.book >= BOOKS.len() {
+            return Err(ParseReferenceError(String::from("Unknown book in passage.")));
+        }
+        let numbers = [self.from.chapter, self.from.verse, self.to.chapter, self.to.verse];
+        if numbers.iter().flatten().any(|&x| x >= MAX_NUMBER) {
+            return Err(ParseReferenceError(String::from("Chapter or verse number too large in passage.")));
+        }
+        if self.from.start() > self.to.end() {
+            return Err(ParseReferenceError(format!("Passage '{}' ends before it starts.", self)));
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, book: usize, chapter: usize, verse: usize) -> bool {
+        self.from.start() <= (book, chapter, verse) && (book, chapter, verse) <= self.to.end()
+    }
+
+    /// Number of verses of the passage in the given bible.
+    pub fn verse_count(&self, bible: &Bible) -> usize {
+        bible.books.iter()
+            .filter(|book| self.from.book <= book.nr && book.nr <= self.to.book)
+            .flat_map(|book| book.chapters.iter().enumerate().map(move |(c, chapter)| (book.nr, c, chapter)))
+            .map(|(book, c, chapter)| (0..chapter.verses.len()).filter(|&v| self.contains(book, c, v)).count())
+            .sum()
+    }
+
+    /// Collects the verses of the passage, grouped by book and chapter.
+    pub fn get_text(&self, bible: &Bible) -> PassageText {
+        let mut books = vec![];
+        for book in bible.books.iter().filter(|book| self.from.book <= book.nr && book.nr <= self.to.book) {
+            let mut chapters = vec![];
+            for (c, chapter) in book.chapters.iter().enumerate() {
+                let verses: Vec<_> = chapter.verses.iter().enumerate()
+                    .filter(|(v, _)| self.contains(book.nr, c, *v))
+                    .map(|(_, verse)| verse.clone())
+                    .collect();
+                if !verses.is_empty() {
+                    chapters.push(Chapter { chapter: chapter.chapter, verses });
+                }
+            }
+            if !chapters.is_empty() {
+                books.push(Book { nr: book.nr, name: BOOKS[book.nr], chapters });
+            }
+        }
+        PassageText { reference: self.to_string(), books }
+    }
+}
+
+impl fmt::Display for Passage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let point = |p: &PassagePoint| match (p.chapter, p.verse) {
+            (Some(chapter), Some(verse)) => format_reference(p.book, chapter, verse),
+            (Some(chapter), None) => format!("{} {}", BOOKS[p.book], chapter.saturating_add(1)),
+            _ => String::from(BOOKS[p.book]),
+        };
+        if self.from == self.to {
+            write!(f, "{}", point(&self.from))
+        } else {
+            write!(f, "{} - {}", point(&self.from), point(&self.to))
+        }
+    }
+}
+
+impl FromStr for Passage {
+    type Err = ParseReferenceError;
+
+    /// Parses a reference with 1-based numbers like `John`, `John 3`, `John 3:16`, `John 3:16-18`,
+    /// `Matthew 5:1-7:29` or `Genesis 50-Exodus 2`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to) = match s.split_once(&['-', '–'][..]) {
+            Some((from, to)) => (from, Some(to.trim())),
+            None => (s, None),
+        };
+        let from = parse_point(from)?;
+        let to = match to {
+            None => from,
+            Some(to) if to.chars().any(|c| c.is_alphabetic()) => parse_point(to)?,
+            Some(to) => match parse_chapter_verse(to)? {
+                // a single number continues the most specific part of the start
+                (Some(nr), None) if from.verse.is_some() => PassagePoint { verse: Some(nr), ..from },
+                (chapter, verse) => PassagePoint { book: from.book, chapter, verse },
+            },
+        };
+        let passage = Self::new(from, to);
+        passage.validate()?;
+        Ok(passage)
+    }
+}
+
+/// Parses a list of references separated by `;`, e.g. `Matthew 5:1-7:29; John 3,16`.
+pub fn parse_passages(s: &str) -> Result<Vec<Passage>, ParseReferenceError> {
+    s.split(';').filter(|x| !x.trim().is_empty()).map(Passage::from_str).collect()
+}
+
+impl PassageQuery {
+    /// The requested passages, from the reference string and the structured list.
+    pub fn passages(&self) -> Result<Vec<Passage>, ParseReferenceError> {
+        let mut passages = match &self.reference {
+            Some(reference) => parse_passages(reference)?,
+            None => vec![],
+        };
+        for passage in self.passages.iter().flatten() {
+            passage.validate()?;
+            passages.push(*passage);
+        }
+        Ok(passages)
+    }
+}
+
+/// Counts the verses of all passages without copying them.
+pub fn passages_verse_count(bible: &Bible, passages: &[Passage]) -> usize {
+    passages.iter().map(|x| x.verse_count(bible)).sum()
+}
+
+pub fn get_passages(bible: &Bible, passages: &[Passage]) -> PassageResult {
+    PassageResult {
+        verse_count: passages_verse_count(bible, passages),
+        passages: passages.iter().map(|x| x.get_text(bible)).collect(),
+    }
+}
@@ -0,0 +1,108 @@
+use crate::BOOKS;
+use serde::{Deserialize, Deserializer, de};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+// Declarations
+#[derive(Debug)]
+pub struct ParseReferenceError(pub String);
+
+/// An inclusive range of books, using the 0-based book numbers of the `Bible` tree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BookRange {
+    pub from: usize,
+    pub to: usize,
+}
+
+// Implementation
+impl fmt::Display for ParseReferenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for ParseReferenceError {}
+
+fn normalize_book_name(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase()
+}
+
+/// Finds the 0-based book number for a book name, an unambiguous prefix of it
+/// (`Matt`, `1Cor`) or a 1-based book number as used in Zefania files.
+pub fn find_book(name: &str) -> Option<usize> {
+    let name = name.trim();
+    if let Ok(nr) = name.parse::<usize>() {
+        return if (1..=BOOKS.len()).contains(&nr) { Some(nr - 1) } else { None };
+    }
+    let name = normalize_book_name(name);
+    if name.is_empty() {
+        return None;
+    }
+    let names: Vec<String> = BOOKS.iter().map(|x| normalize_book_name(x)).collect();
+    if let Some(i) = names.iter().position(|x| *x == name) {
+        return Some(i);
+    }
+    let candidates: Vec<usize> = (0..names.len()).filter(|&i| names[i].starts_with(&name)).collect();
+    if candidates.len() == 1 {
+        Some(candidates[0])
+    } else {
+        None
+    }
+}
+
+/// Formats a reference of the `Bible` tree for humans, e.g. `John 3,16`.
+pub fn format_reference(book: usize, chapter: usize, verse: usize) -> String {
+    format!("{} {},{}", BOOKS.get(book).copied().unwrap_or("Unknown"), chapter.saturating_add(1), verse.saturating_add(1))
+}
+
+impl BookRange {
+    pub fn new(from: usize, to: usize) -> Self {
+        Self { from, to }
+    }
+
+    pub fn old_testament() -> Self {
+        Self::new(0, 38)
+    }
+
+    pub fn new_testament() -> Self {
+        Self::new(39, BOOKS.len() - 1)
+    }
+
+    pub fn contains(&self, book: usize) -> bool {
+        self.from <= book && book <= self.to
+    }
+}
+
+impl FromStr for BookRange {
+    type Err = ParseReferenceError;
+
+    /// Parses `OT`, `NT`, a single book (`John`) or a range of books (`Matthew-John`, `40-43`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "ot" => return Ok(Self::old_testament()),
+            "nt" => return Ok(Self::new_testament()),
+            _ => (),
+        }
+        let book = |name: &str| find_book(name).ok_or_else(|| ParseReferenceError(format!("Unknown book '{}'.", name.trim())));
+        let range = if let Some((from, to)) = s.split_once('-') {
+            Self::new(book(from)?, book(to)?)
+        } else {
+            let nr = book(s)?;
+            Self::new(nr, nr)
+        };
+        if range.from > range.to {
+            return Err(ParseReferenceError(format!("Invalid book range '{}'.", s)));
+        }
+        Ok(range)
+    }
+}
+
+impl<'de> Deserialize<'de> for BookRange {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
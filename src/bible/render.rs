@@ -0,0 +1,230 @@
+use crate::{Bible, Chapter, Chunk, PassageText, StrongKey, VerseRef, BOOKS};
+use serde::Deserialize;
+use std::fmt::Write;
+
+// Declarations
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// Every verse on its own line
+    Lines,
+    /// The verses of a chapter as one paragraph
+    Paragraph,
+}
+
+/// How verses are rendered as plain text or html.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderOptions {
+    pub verse_numbers: bool,
+    /// Strong numbers after the tagged words, e.g. `loved[G25]`
+    pub strongs: bool,
+    pub layout: Layout,
+}
+
+/// A part of a rendered verse, for renderers which style or wrap the words.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VersePart<'a> {
+    /// The 1-based verse number
+    Number(usize),
+    /// A word of the text, `red` for the words of Jesus
+    Word { text: &'a str, red: bool },
+    /// The strong number of the word before it
+    Strong(StrongKey),
+}
+
+/// Verses of one chapter to render, e.g. a whole chapter or the search results in it.
+pub struct ChapterText<'a> {
+    pub book: usize,
+    pub chapter: usize,
+    /// The 0-based verse numbers with their chunks
+    pub verses: Vec<(usize, &'a [Chunk])>,
+}
+
+// Implementation
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self { verse_numbers: true, strongs: false, layout: Layout::Lines }
+    }
+}
+
+impl<'a> ChapterText<'a> {
+    pub fn new(book: usize, chapter: &'a Chapter) -> Self {
+        Self { book, chapter: chapter.chapter, verses: chapter.verses.iter().map(|x| (x.verse, x.chunks.as_slice())).collect() }
+    }
+
+    /// Groups verse references with chunks by chapter, keeping their order.
+    pub fn group(verses: &'a [VerseRef]) -> Vec<Self> {
+        let mut chapters: Vec<Self> = vec![];
+        for verse in verses {
+            let chunks = verse.chunks.as_deref().unwrap_or(&[]);
+            match chapters.last_mut() {
+                Some(last) if last.book == verse.book && last.chapter == verse.chapter => last.verses.push((verse.verse, chunks)),
+                _ => chapters.push(Self { book: verse.book, chapter: verse.chapter, verses: vec![(verse.verse, chunks)] }),
+            }
+        }
+        chapters
+    }
+
+    pub fn from_passages(passages: &'a [PassageText]) -> Vec<Self> {
+        passages.iter()
+            .flat_map(|x| x.books.iter())
+            .flat_map(|book| book.chapters.iter().map(move |chapter| Self::new(book.nr, chapter)))
+            .collect()
+    }
+
+    /// The heading of the chapter, e.g. `John 3`.
+    pub fn title(&self) -> String {
+        format!("{} {}", BOOKS.get(self.book).copied().unwrap_or("Unknown"), self.chapter + 1)
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn chunk_strong(book: usize, chunk: &Chunk) -> Option<StrongKey> {
+    chunk.strong.as_ref().map(|x| StrongKey::for_book(book, x.number))
+}
+
+/// The parts of a verse as the options render it, with the text split into words.
+pub fn verse_parts<'a>(book: usize, verse: usize, chunks: &'a [Chunk], options: &RenderOptions) -> Vec<VersePart<'a>> {
+    let mut parts = vec![];
+    if options.verse_numbers {
+        parts.push(VersePart::Number(verse + 1));
+    }
+    for chunk in chunks {
+        let len = parts.len();
+        parts.extend(chunk.text.split_whitespace().map(|text| VersePart::Word { text, red: chunk.red }));
+        match chunk_strong(book, chunk) {
+            Some(key) if options.strongs && parts.len() > len => parts.push(VersePart::Strong(key)),
+            _ => {},
+        }
+    }
+    parts
+}
+
+/// Joins the rendered parts with spaces, the strong numbers follow their word without one.
+fn join_parts(parts: &[VersePart], render: impl Fn(&VersePart) -> String) -> String {
+    let mut out = String::new();
+    for part in parts {
+        if !out.is_empty() && !matches!(part, VersePart::Strong(_)) {
+            out.push(' ');
+        }
+        out.push_str(&render(part));
+    }
+    out
+}
+
+/// The verse as plain text, e.g. `16 For God so loved[G25] the world`.
+fn parts_text(parts: &[VersePart]) -> String {
+    join_parts(parts, |part| match part {
+        VersePart::Number(number) => number.to_string(),
+        VersePart::Word { text, .. } => String::from(*text),
+        VersePart::Strong(key) => format!("[{}]", key),
+    })
+}
+
+/// Renders the chapters as plain text, each with its heading and a blank line in between.
+pub fn render_text(chapters: &[ChapterText], options: &RenderOptions) -> String {
+    let mut out = String::new();
+    for (i, chapter) in chapters.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "{}", chapter.title());
+        let verses: Vec<String> = chapter.verses.iter()
+            .map(|(verse, chunks)| parts_text(&verse_parts(chapter.book, *verse, chunks, options)))
+            .collect();
+        match options.layout {
+            Layout::Lines => verses.iter().for_each(|x| { let _ = writeln!(out, "{}", x); }),
+            Layout::Paragraph => { let _ = writeln!(out, "{}", verses.join(" ")); },
+        }
+    }
+    out
+}
+
+/// Renders the chapters as html document with a section per chapter.
+pub fn render_html(title: &str, chapters: &[ChapterText], options: &RenderOptions) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    let _ = writeln!(out, "<title>{}</title>\n</head>\n<body>", escape(title));
+    for chapter in chapters {
+        let _ = writeln!(out, "<section>\n<h2>{}</h2>", escape(&chapter.title()));
+        let verses: Vec<String> = chapter.verses.iter()
+            .map(|(verse, chunks)| join_parts(&verse_parts(chapter.book, *verse, chunks, options), |part| match part {
+                VersePart::Number(number) => format!("<sup class=\"verse\">{}</sup>", number),
+                VersePart::Word { text, .. } => escape(text),
+                VersePart::Strong(key) => format!("<sup class=\"strong\">{}</sup>", key),
+            }))
+            .collect();
+        match options.layout {
+            Layout::Lines => verses.iter().for_each(|x| { let _ = writeln!(out, "<p>{}</p>", x); }),
+            Layout::Paragraph => { let _ = writeln!(out, "<p>{}</p>", verses.join(" ")); },
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Renders the chapters as Zefania XML with the words tagged by `gr` elements if
+/// strong numbers are requested. Verse numbers and layout do not apply.
+pub fn render_xml(name: &str, chapters: &[ChapterText], options: &RenderOptions) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    let _ = writeln!(out, "<XMLBIBLE biblename=\"{}\">", escape(name));
+    write_xml_books(&mut out, chapters, options.strongs);
+    out.push_str("</XMLBIBLE>\n");
+    out
+}
+
+/// Renders the whole bible as Zefania XML file, with its name and identifier and
+/// all strong numbers, which the parser reads back unchanged.
+pub fn render_zefania(bible: &Bible) -> String {
+    let chapters: Vec<ChapterText> = bible.books.iter()
+        .flat_map(|book| book.chapters.iter().map(move |chapter| ChapterText::new(book.nr, chapter)))
+        .collect();
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    let _ = writeln!(out, "<XMLBIBLE biblename=\"{}\">", escape(&bible.name));
+    out.push_str("  <INFORMATION>\n");
+    let _ = writeln!(out, "    <title>{}</title>\n    <identifier>{}</identifier>", escape(&bible.name), escape(&bible.identifier));
+    out.push_str("  </INFORMATION>\n");
+    write_xml_books(&mut out, &chapters, true);
+    out.push_str("</XMLBIBLE>\n");
+    out
+}
+
+/// The `BIBLEBOOK` elements of the chapters, with the words of Jesus in red `STYLE` elements.
+fn write_xml_books(out: &mut String, chapters: &[ChapterText], strongs: bool) {
+    let mut book = None;
+    for chapter in chapters {
+        if book != Some(chapter.book) {
+            if book.is_some() {
+                out.push_str("  </BIBLEBOOK>\n");
+            }
+            let _ = writeln!(out, "  <BIBLEBOOK bnumber=\"{}\" bname=\"{}\">", chapter.book + 1, BOOKS.get(chapter.book).copied().unwrap_or("Unknown"));
+            book = Some(chapter.book);
+        }
+        let _ = writeln!(out, "    <CHAPTER cnumber=\"{}\">", chapter.chapter + 1);
+        for (verse, chunks) in &chapter.verses {
+            let text = chunks.iter()
+                .map(|chunk| {
+                    let text = match &chunk.strong {
+                        Some(strong) if strongs => match &strong.grammar {
+                            Some(grammar) => format!("<gr str=\"{}\" rmac=\"{}\">{}</gr>", strong.number, escape(grammar), escape(&chunk.text)),
+                            None => format!("<gr str=\"{}\">{}</gr>", strong.number, escape(&chunk.text)),
+                        },
+                        _ => escape(&chunk.text),
+                    };
+                    if chunk.red { format!("<STYLE css=\"color:#FF0000\">{}</STYLE>", text) } else { text }
+                })
+                .collect::<Vec<String>>()
+                .join(" ");
+            let _ = writeln!(out, "      <VERS vnumber=\"{}\">{}</VERS>", verse + 1, text);
+        }
+        out.push_str("    </CHAPTER>\n");
+    }
+    if book.is_some() {
+        out.push_str("  </BIBLEBOOK>\n");
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{Bible, Chunk, Losses, BOOKS, StrongKey, StrongNumber, Verse, content_hash};
+use crate::{BibleExporter, BibleParser};
+use rusqlite::{Connection, params};
+use std::error::Error;
+
+// Declarations
+/// Reads and writes a SQLite database with the text of each verse for queries and
+/// the tagged words, numbered from 1 like the references.
+pub struct SqliteBible;
+
+const SCHEMA: &str = "
+CREATE TABLE translation (identifier TEXT NOT NULL, name TEXT NOT NULL);
+CREATE TABLE verses (
+    book INTEGER NOT NULL, chapter INTEGER NOT NULL, verse INTEGER NOT NULL, text TEXT NOT NULL,
+    PRIMARY KEY (book, chapter, verse)
+);
+CREATE TABLE words (
+    book INTEGER NOT NULL, chapter INTEGER NOT NULL, verse INTEGER NOT NULL, position INTEGER NOT NULL,
+    text TEXT NOT NULL, strong TEXT, grammar TEXT, red INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (book, chapter, verse, position)
+);
+CREATE INDEX words_strong ON words (strong);
+";
+
+// Implementation
+impl BibleParser for SqliteBible {
+    fn parse(path: &str) -> Result<Bible, Box<dyn Error>> {
+        let connection = Connection::open(path)?;
+        let mut bible = Bible::new("Unknown", "Unknown translation");
+        (bible.identifier, bible.name) = connection.query_row("SELECT identifier, name FROM translation", [], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut statement = connection.prepare(
+            "SELECT v.book, v.chapter, v.verse, w.text, w.strong, w.grammar, w.red FROM verses v
+             LEFT JOIN words w ON w.book = v.book AND w.chapter = v.chapter AND w.verse = v.verse
+             ORDER BY v.book, v.chapter, v.verse, w.position")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let (book, chapter, verse): (usize, usize, usize) = (row.get(0)?, row.get(1)?, row.get(2)?);
+            let (book, chapter, verse) = match (book.checked_sub(1), chapter.checked_sub(1), verse.checked_sub(1)) {
+                (Some(b), _, _) if b >= BOOKS.len() => return Err(From::from(format!("{}: invalid book number {}, there are {} books.", path, book, BOOKS.len()))),
+                (Some(book), Some(chapter), Some(verse)) => (book, chapter, verse),
+                _ => return Err(From::from(format!("{}: invalid reference {} {},{}, numbers start at 1.", path, book, chapter, verse))),
+            };
+            let chapter = bible.get_chapter_mut(book, chapter);
+            if chapter.verses.last().map(|x| x.verse) != Some(verse) {
+                chapter.add_verse(Verse::new(verse));
+            }
+            if let Some(text) = row.get::<_, Option<String>>(3)? {
+                let strong: Option<String> = row.get(4)?;
+                let strong = match strong.map(|x| x.parse::<StrongKey>()).transpose() {
+                    Ok(strong) => strong.map(|x| StrongNumber::new(x.number as i32, row.get(5).ok().flatten())),
+                    Err(e) => return Err(From::from(e.to_string())),
+                };
+                chapter.verses.last_mut().unwrap().add_strong(Chunk { text, strong, red: row.get(6)? });
+            }
+        }
+        bible.index();
+        bible.content_hash = content_hash(&std::fs::read(path)?);
+        Ok(bible)
+    }
+}
+
+impl BibleExporter for SqliteBible {
+    fn write(bible: &Bible, path: &str, _losses: &mut Losses) -> Result<(), Box<dyn Error>> {
+        if std::path::Path::new(path).exists() {
+            std::fs::remove_file(path)?;
+        }
+        let mut connection = Connection::open(path)?;
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(SCHEMA)?;
+        transaction.execute("INSERT INTO translation (identifier, name) VALUES (?1, ?2)", params![bible.identifier, bible.name])?;
+        {
+            let mut verses = transaction.prepare("INSERT INTO verses (book, chapter, verse, text) VALUES (?1, ?2, ?3, ?4)")?;
+            let mut words = transaction.prepare(
+                "INSERT INTO words (book, chapter, verse, position, text, strong, grammar, red) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)")?;
+            for book in &bible.books {
+                for chapter in &book.chapters {
+                    for verse in &chapter.verses {
+                        let (b, c, v) = (book.nr + 1, chapter.chapter + 1, verse.verse + 1);
+                        verses.execute(params![b, c, v, verse.to_string()])?;
+                        for (i, chunk) in verse.chunks.iter().enumerate() {
+                            let strong = chunk.strong.as_ref().map(|x| StrongKey::for_book(book.nr, x.number).to_string());
+                            let grammar = chunk.strong.as_ref().and_then(|x| x.grammar.as_deref());
+                            words.execute(params![b, c, v, i + 1, chunk.text, strong, grammar, chunk.red])?;
+                        }
+                    }
+                }
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{Bible, BookRange, StrongKey, Verse, BOOKS, tokenize, normalize_word};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// Declarations
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum StatsUnit {
+    /// Count the words as written, lowercased and without punctuation.
+    Word,
+    /// Count the strong numbers of the tagged chunks.
+    Strong,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams), into_params(parameter_in = Query))]
+pub struct StatsQuery {
+    /// Count words or strong numbers (default: word)
+    pub unit: Option<StatsUnit>,
+    /// Book range, e.g. `NT`, `John` or `Matthew-John` (default: all books)
+    #[cfg_attr(feature = "utoipa", param(value_type = Option<String>))]
+    pub books: Option<BookRange>,
+    /// Number of most frequent items to list (default: 50)
+    pub top: Option<usize>,
+    /// Include the list of hapax legomena
+    pub hapax: Option<bool>,
+    /// Word or strong number to show the per book frequencies for
+    pub item: Option<String>,
+}
+
+/// Token counts of a set of books, overall and per book.
+pub struct Vocabulary {
+    pub unit: StatsUnit,
+    pub tokens: u64,
+    pub counts: HashMap<String, u64>,
+    pub books: Vec<BookVocabulary>,
+}
+
+pub struct BookVocabulary {
+    pub book: usize,
+    pub tokens: u64,
+    pub counts: HashMap<String, u64>,
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct Frequency {
+    pub item: String,
+    pub count: u64,
+    pub per_thousand: f64,
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct BookStats {
+    pub book: usize,
+    pub name: &'static str,
+    pub tokens: u64,
+    pub types: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<Frequency>,
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct VocabularyStats {
+    pub unit: StatsUnit,
+    pub tokens: u64,
+    pub types: u64,
+    pub hapax_count: u64,
+    pub frequencies: Vec<Frequency>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hapax_legomena: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<Frequency>,
+    pub books: Vec<BookStats>,
+}
+
+pub const DEFAULT_STATS_TOP: usize = 50;
+
+fn per_thousand(count: u64, tokens: u64) -> f64 {
+    if tokens == 0 { 0.0 } else { count as f64 * 1000.0 / tokens as f64 }
+}
+
+fn verse_items(book: usize, verse: &Verse, unit: StatsUnit) -> Vec<String> {
+    match unit {
+        StatsUnit::Word => tokenize(book, verse).into_iter().map(|x| x.word).collect(),
+        StatsUnit::Strong => verse.chunks.iter()
+            .filter_map(|chunk| chunk.strong.as_ref().map(|x| StrongKey::for_book(book, x.number).to_string()))
+            .collect(),
+    }
+}
+
+// Implementation
+impl Vocabulary {
+    pub fn build(bible: &Bible, unit: StatsUnit, books: Option<BookRange>) -> Self {
+        let mut vocabulary = Self { unit, tokens: 0, counts: HashMap::new(), books: vec![] };
+        for book in bible.books.iter().filter(|x| books.iter().all(|range| range.contains(x.nr))) {
+            let mut book_vocabulary = BookVocabulary { book: book.nr, tokens: 0, counts: HashMap::new() };
+            for chapter in &book.chapters {
+                for verse in &chapter.verses {
+                    for item in verse_items(book.nr, verse, unit) {
+                        book_vocabulary.tokens += 1;
+                        *book_vocabulary.counts.entry(item).or_insert(0) += 1;
+                    }
+                }
+            }
+            vocabulary.tokens += book_vocabulary.tokens;
+            for (item, count) in &book_vocabulary.counts {
+                *vocabulary.counts.entry(item.clone()).or_insert(0) += count;
+            }
+            vocabulary.books.push(book_vocabulary);
+        }
+        vocabulary
+    }
+
+    /// Brings a word or strong number into the form used as key in `counts`.
+    pub fn normalize_item(&self, item: &str) -> String {
+        match self.unit {
+            StatsUnit::Word => normalize_word(item),
+            StatsUnit::Strong => StrongKey::from_str(item).map(|x| x.to_string()).unwrap_or_else(|_| item.to_uppercase()),
+        }
+    }
+
+    pub fn types(&self) -> u64 {
+        self.counts.len() as u64
+    }
+
+    pub fn frequency(&self, item: &str) -> Frequency {
+        let item = self.normalize_item(item);
+        let count = self.counts.get(&item).copied().unwrap_or(0);
+        Frequency { per_thousand: per_thousand(count, self.tokens), item, count }
+    }
+
+    /// All items ordered by descending count, ties ordered alphabetically.
+    pub fn frequencies(&self) -> Vec<Frequency> {
+        let mut res: Vec<Frequency> = self.counts.iter()
+            .map(|(item, &count)| Frequency { item: item.clone(), count, per_thousand: per_thousand(count, self.tokens) })
+            .collect();
+        res.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.item.cmp(&b.item)));
+        res
+    }
+
+    /// Items occurring exactly once in the selected books.
+    pub fn hapax_legomena(&self) -> Vec<String> {
+        let mut res: Vec<String> = self.counts.iter().filter(|(_, &count)| count == 1).map(|(item, _)| item.clone()).collect();
+        res.sort();
+        res
+    }
+
+    pub fn book_stats(&self, item: Option<&str>) -> Vec<BookStats> {
+        let item = item.map(|x| self.normalize_item(x));
+        self.books.iter().map(|book| BookStats {
+            book: book.book,
+            name: BOOKS.get(book.book).copied().unwrap_or(""),
+            tokens: book.tokens,
+            types: book.counts.len() as u64,
+            item: item.as_ref().map(|item| {
+                let count = book.counts.get(item).copied().unwrap_or(0);
+                Frequency { item: item.clone(), count, per_thousand: per_thousand(count, book.tokens) }
+            }),
+        }).collect()
+    }
+
+    pub fn stats(&self, query: &StatsQuery) -> VocabularyStats {
+        let hapax_legomena = self.hapax_legomena();
+        let mut frequencies = self.frequencies();
+        frequencies.truncate(query.top.unwrap_or(DEFAULT_STATS_TOP));
+        VocabularyStats {
+            unit: self.unit,
+            tokens: self.tokens,
+            types: self.types(),
+            hapax_count: hapax_legomena.len() as u64,
+            frequencies,
+            hapax_legomena: if query.hapax.unwrap_or(false) { Some(hapax_legomena) } else { None },
+            item: query.item.as_ref().map(|x| self.frequency(x)),
+            books: self.book_stats(query.item.as_deref()),
+        }
+    }
+}
+
+/// Computes the vocabulary statistics of a bible as requested by `query`.
+pub fn vocabulary_stats(bible: &Bible, query: &StatsQuery) -> VocabularyStats {
+    Vocabulary::build(bible, query.unit.unwrap_or(StatsUnit::Word), query.books).stats(query)
+}
+
+impl FromStr for StatsUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "word" => Ok(Self::Word),
+            "strong" => Ok(Self::Strong),
+            _ => Err(format!("Unknown unit '{}'.", s)),
+        }
+    }
+}
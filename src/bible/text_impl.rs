@@ -0,0 +1,23 @@
+use crate::{Bible, ChapterText, Losses, RenderOptions, render_text};
+use crate::BibleExporter;
+use std::error::Error;
+
+// Declarations
+/// Writes a bible as plain text with a heading per chapter and a line per verse.
+pub struct TextBible;
+
+// Implementation
+impl BibleExporter for TextBible {
+    fn write(bible: &Bible, path: &str, losses: &mut Losses) -> Result<(), Box<dyn Error>> {
+        let chapters: Vec<ChapterText> = bible.books.iter()
+            .flat_map(|book| book.chapters.iter().map(move |chapter| ChapterText::new(book.nr, chapter)))
+            .collect();
+        let chunks = || chapters.iter().flat_map(|x| x.verses.iter()).flat_map(|(_, chunks)| chunks.iter());
+        losses.add("strong numbers", chunks().filter(|x| x.strong.is_some()).count());
+        losses.add("grammar codes", chunks().filter(|x| x.strong.as_ref().is_some_and(|x| x.grammar.is_some())).count());
+        losses.add("red letter passages", chunks().filter(|x| x.red).count());
+        let text = format!("{}\n\n{}", bible.name, render_text(&chapters, &RenderOptions::default()));
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
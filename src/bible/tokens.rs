@@ -0,0 +1,35 @@
+use crate::{StrongKey, Verse};
+
+// Declarations
+/// A single word of a verse. `word` is the normalized form used for counting
+/// and matching, `text` the word as written in the translation and `chunk` the
+/// index of the chunk the word belongs to.
+#[derive(Clone, Debug)]
+pub struct Token<'a> {
+    pub text: &'a str,
+    pub word: String,
+    pub strong: Option<StrongKey>,
+    pub chunk: usize,
+}
+
+// Implementation
+/// Lowercases a word and strips leading and trailing punctuation.
+pub fn normalize_word(text: &str) -> String {
+    text.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+}
+
+/// Splits the chunks of a verse into words. Every word of a tagged chunk
+/// carries the strong number of that chunk, pure punctuation is dropped.
+pub fn tokenize(book: usize, verse: &Verse) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    for (i, chunk) in verse.chunks.iter().enumerate() {
+        let strong = chunk.strong.as_ref().map(|x| StrongKey::for_book(book, x.number));
+        for text in chunk.text.split_whitespace() {
+            let word = normalize_word(text);
+            if !word.is_empty() {
+                tokens.push(Token { text, word, strong, chunk: i });
+            }
+        }
+    }
+    tokens
+}
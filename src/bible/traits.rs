@@ -1,27 +1,108 @@
-use crate::{Bible, VerseRef};
+use crate::{Bible, BookRange, Chapter, Losses, Verse, VerseRef, BOOKS};
+use serde::{Deserialize, Serialize};
 use std::error::Error;
 use rayon::prelude::*;
-use std::sync::{Arc, Mutex};
-use std::iter::Flatten;
 use shellwords;
 
 pub trait BibleParser {
     fn parse(path: &str) -> Result<Bible, Box<dyn Error>>;
+
+    /// Parses the file and counts what the bible cannot hold, e.g. notes or headings.
+    fn parse_with_losses(path: &str, _losses: &mut Losses) -> Result<Bible, Box<dyn Error>> {
+        Self::parse(path)
+    }
 }
 
 pub trait BibleExporter {
-    fn write(path: String) -> Result<(), Box<dyn Error>>;
+    /// Writes the bible to `path` and counts what the format cannot represent.
+    fn write(bible: &Bible, path: &str, losses: &mut Losses) -> Result<(), Box<dyn Error>>;
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Verses containing all words, words in quotes are searched as phrase.
+    All,
+    /// Verses containing at least one of the words.
+    Any,
+    /// Verses containing the whole search text as it is.
+    Phrase,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams, utoipa::ToSchema), into_params(parameter_in = Query))]
+pub struct SearchQuery {
+    /// Search text
+    pub q: String,
+    /// Book range to search in, e.g. `NT`, `John` or `Matthew-John` (default: all books)
+    #[cfg_attr(feature = "utoipa", param(value_type = Option<String>), schema(value_type = Option<String>))]
+    pub scope: Option<BookRange>,
+    /// How the words of the search text are combined (default: all)
+    pub mode: Option<SearchMode>,
+    /// Number of verses to skip
+    pub offset: Option<usize>,
+    /// Maximum number of verses to return
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct SearchResult {
+    pub total: usize,
+    pub offset: usize,
+    pub limit: Option<usize>,
+    pub verses: Vec<VerseRef>,
 }
 
 pub trait BibleSearcher {
     fn search(self: &Self, search_text: &String) -> Result<Vec<VerseRef>, Box<dyn Error>>;
     fn search_parallel(self: &Self, search_text: &String) -> Result<Vec<VerseRef>, Box<dyn Error>>;
+    fn search_with(&self, query: &SearchQuery) -> Result<SearchResult, Box<dyn Error>>;
+    fn search_book(&self, query: &SearchQuery, book: usize) -> Result<Vec<VerseRef>, Box<dyn Error>>;
+}
+
+impl SearchQuery {
+    pub fn new(q: &str) -> Self {
+        Self { q: String::from(q), ..Default::default() }
+    }
+
+    fn words(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(match self.mode.unwrap_or(SearchMode::All) {
+            SearchMode::Phrase => vec![self.q.to_lowercase()],
+            _ => shellwords::split(&self.q.to_lowercase())?,
+        })
+    }
+
+    /// Rough cost of the query in scans of a whole bible. Every word is matched
+    /// against every verse of the scope, and words shorter than three letters
+    /// match most verses, which makes collecting their results expensive.
+    pub fn estimated_cost(&self) -> Result<usize, Box<dyn Error>> {
+        let words: usize = self.words()?.iter()
+            .map(|x| if x.chars().count() < 3 { 4 } else { 1 })
+            .sum();
+        let books = self.scope.map(|x| x.to.saturating_sub(x.from) + 1).unwrap_or(BOOKS.len()).min(BOOKS.len());
+        Ok((words * books).div_ceil(BOOKS.len()).max(1))
+    }
+
+    /// Returns a matcher telling whether the text of a verse fulfills the query.
+    fn matcher(&self) -> Result<impl Fn(&Verse) -> bool, Box<dyn Error>> {
+        let mode = self.mode.unwrap_or(SearchMode::All);
+        let words = self.words()?;
+        Ok(move |verse: &Verse| {
+            let text = verse.to_string().to_lowercase();
+            match mode {
+                SearchMode::Any => words.iter().any(|word| text.contains(word.as_str())),
+                _ => words.iter().all(|word| text.contains(word.as_str())),
+            }
+        })
+    }
 }
 
 impl BibleSearcher for Bible {
     fn search(self: &Self, search_text: &String) -> Result<Vec<VerseRef>, Box<dyn Error>> {
         let words = shellwords::split(&search_text.to_lowercase())?;
-        println!("search_text: {}, words: {:?}", search_text, &words);
+        log::debug!("search_text: {}, words: {:?}", search_text, &words);
         let mut res = vec![];
         for book in &self.books {
             for chapter in &book.chapters {
@@ -42,21 +123,49 @@ impl BibleSearcher for Bible {
         Ok(res)
     }
 
+    fn search_with(&self, query: &SearchQuery) -> Result<SearchResult, Box<dyn Error>> {
+        let matches = query.matcher()?;
+        let offset = query.offset.unwrap_or(0);
+        let limit = query.limit.unwrap_or(usize::MAX);
+        // Match the books in parallel, but only clone the chunks of the requested page
+        let found: Vec<Vec<(usize, &Chapter, &Verse)>> = self.books.par_iter()
+            .filter(|x| query.scope.iter().all(|scope| scope.contains(x.nr)))
+            .map(|book| book.chapters.iter()
+                .flat_map(|chapter| chapter.verses.iter().filter(|x| matches(x)).map(move |verse| (book.nr, chapter, verse)))
+                .collect())
+            .collect();
+        let total = found.iter().map(|x| x.len()).sum();
+        let verses = found.into_iter().flatten().skip(offset).take(limit)
+            .map(|(book, chapter, verse)| VerseRef::new_with_chunks(book, chapter.chapter, verse.verse, verse.chunks.clone()))
+            .collect();
+        Ok(SearchResult { total, offset, limit: query.limit, verses })
+    }
+
+    /// Searches a single book, ignoring the scope, offset and limit of the query,
+    /// so the results of a large search can be sent book by book.
+    fn search_book(&self, query: &SearchQuery, book: usize) -> Result<Vec<VerseRef>, Box<dyn Error>> {
+        let matches = query.matcher()?;
+        Ok(self.get_book(book).iter()
+            .flat_map(|x| x.chapters.iter())
+            .flat_map(|chapter| chapter.verses.iter().filter(|x| matches(x)).map(move |verse| (chapter, verse)))
+            .map(|(chapter, verse)| VerseRef::new_with_chunks(book, chapter.chapter, verse.verse, verse.chunks.clone()))
+            .collect())
+    }
+
     fn search_parallel(self: &Self, search_text: &String) -> Result<Vec<VerseRef>, Box<dyn Error>> {
-        // let res: Vec<Vec<VerseRef>> = &self.books.par_iter().map(|book| {
-        //     let mut par_res = vec![];
-        //     for chapter in &book.chapters {
-        //         for verse in &chapter.verses {
-        //             for chunk in &verse.chunks {
-        //                 if let Some(_) = chunk.text.find(search_text) {
-        //                     par_res.push(VerseRef::new(book.nr, chapter.chapter, verse.verse));
-        //                 }
-        //             }
-        //         }
-        //     }
-        //     par_res
-        // }).collect();
-        // Ok(res.concat())
-        self.search(search_text)
+        let words = shellwords::split(&search_text.to_lowercase())?;
+        let res: Vec<Vec<VerseRef>> = self.books.par_iter().map(|book| {
+            let mut par_res = vec![];
+            for chapter in &book.chapters {
+                for verse in &chapter.verses {
+                    let text = verse.to_string().to_lowercase();
+                    if words.iter().all(|word| text.contains(word.as_str())) {
+                        par_res.push(VerseRef::new_with_chunks(book.nr, chapter.chapter, verse.verse, verse.chunks.clone()));
+                    }
+                }
+            }
+            par_res
+        }).collect();
+        Ok(res.concat())
     }
 }
\ No newline at end of file
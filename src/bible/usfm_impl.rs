@@ -0,0 +1,256 @@
+use crate::{Bible, Chunk, Losses, StrongKey, StrongNumber, Verse, content_hash};
+use crate::{BibleExporter, BibleParser};
+use std::error::Error;
+use std::fmt::Write;
+use std::path::Path;
+
+// Declarations
+/// Reads and writes USFM 3, a file per book. Strong numbers and grammar are `\w`
+/// attributes, words of Jesus `\wj`. Footnotes, cross references, headings and
+/// introductions are left out when reading.
+pub struct UsfmBible;
+
+/// The USFM book codes in the order of `BOOKS`.
+static USFM_BOOKS: &[&str] = &[
+    "GEN", "EXO", "LEV", "NUM", "DEU", "JOS", "JDG", "RUT", "1SA", "2SA", "1KI", "2KI", "1CH", "2CH", "EZR", "NEH", "EST",
+    "JOB", "PSA", "PRO", "ECC", "SNG", "ISA", "JER", "LAM", "EZK", "DAN", "HOS", "JOL", "AMO", "OBA", "JON", "MIC", "NAM",
+    "HAB", "ZEP", "HAG", "ZEC", "MAL", "MAT", "MRK", "LUK", "JHN", "ACT", "ROM", "1CO", "2CO", "GAL", "EPH", "PHP", "COL",
+    "1TH", "2TH", "1TI", "2TI", "TIT", "PHM", "HEB", "JAS", "1PE", "2PE", "1JN", "2JN", "3JN", "JUD", "REV",
+];
+
+/// Notes whose content up to the closing marker is left out.
+static NOTES: &[(&str, &str)] = &[("f", "footnotes"), ("fe", "footnotes"), ("x", "cross references")];
+
+/// Markers whose text up to the next marker is no verse text.
+static HEADINGS: &[&str] = &["s", "s1", "s2", "s3", "s4", "ms", "ms1", "ms2", "ms3", "mr", "r", "d", "sp", "sr"];
+static METADATA: &[&str] = &["ide", "h", "toc1", "toc2", "toc3", "mt", "mt1", "mt2", "mt3", "mt4", "usfm", "sts", "cl"];
+
+/// Introduction markers like `ip` or `is1`, but not `id`, `ide` and the italics `it`.
+fn is_introduction(marker: &str) -> bool {
+    marker.starts_with('i') && !marker.ends_with('*') && !["id", "ide", "it"].contains(&marker)
+}
+
+enum Token<'a> {
+    /// A marker without the backslash, e.g. `v`, `w` or `w*`
+    Marker(&'a str),
+    Text(&'a str),
+}
+
+// Implementation
+/// Splits USFM into markers and the text between them, dropping the space after a marker.
+fn tokens(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut rest = text;
+    while !rest.is_empty() {
+        match rest.strip_prefix('\\') {
+            Some(marker) => {
+                let end = marker.find(|c: char| !(c.is_ascii_alphanumeric() || c == '+' || c == '-')).unwrap_or(marker.len());
+                let end = if marker[end..].starts_with('*') { end + 1 } else { end };
+                tokens.push(Token::Marker(&marker[..end]));
+                rest = &marker[end..];
+                if !marker[..end].ends_with('*') {
+                    rest = rest.strip_prefix(|c: char| c.is_whitespace()).unwrap_or(rest);
+                }
+            },
+            None => {
+                let end = rest.find('\\').unwrap_or(rest.len());
+                tokens.push(Token::Text(&rest[..end]));
+                rest = &rest[end..];
+            },
+        }
+    }
+    tokens
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// The value of an attribute of a `\w` word, e.g. `strong="G25"`.
+fn word_attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let start = attributes.find(&format!("{}=\"", name))? + name.len() + 2;
+    attributes[start..].split('"').next()
+}
+
+/// The content of the file, or of all usfm files of the directory in the order of their names.
+fn read_files(path: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let path = Path::new(path);
+    if !path.is_dir() {
+        return Ok(vec![(path.display().to_string(), std::fs::read_to_string(path)?)]);
+    }
+    let mut files = vec![];
+    for entry in std::fs::read_dir(path)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        if name.ends_with(".usfm") || name.ends_with(".sfm") {
+            files.push((entry.path().display().to_string(), std::fs::read_to_string(entry.path())?));
+        }
+    }
+    files.sort();
+    if files.is_empty() {
+        return Err(From::from(format!("{} contains no .usfm or .sfm files.", path.display())));
+    }
+    Ok(files)
+}
+
+impl BibleParser for UsfmBible {
+    fn parse(path: &str) -> Result<Bible, Box<dyn Error>> {
+        Self::parse_with_losses(path, &mut Losses::default())
+    }
+
+    fn parse_with_losses(path: &str, losses: &mut Losses) -> Result<Bible, Box<dyn Error>> {
+        let files = read_files(path)?;
+        let mut bible = Bible::new("Unknown", "Unknown translation");
+        for (file, text) in &files {
+            let (mut book, mut chapter, mut verse): (Option<usize>, Option<usize>, Option<usize>) = (None, None, None);
+            // the marker the following text belongs to, a `\w` word, and the open note
+            let (mut marker, mut word, mut note): (&str, Option<String>, Option<&str>) = ("", None, None);
+            let mut red = false;
+            for token in tokens(text) {
+                let text = match token {
+                    Token::Marker(name) => {
+                        let name = name.trim_start_matches('+');
+                        match note {
+                            Some(open) if name.strip_suffix('*') == Some(open) => note = None,
+                            Some(_) => {},
+                            None => match name {
+                                "wj" => red = true,
+                                "wj*" => red = false,
+                                "w" => word = Some(String::new()),
+                                "w*" => if let (Some(text), Some(b), Some(c), Some(v)) = (word.take(), book, chapter, verse) {
+                                    let (text, attributes) = text.split_once('|').unwrap_or((&text, ""));
+                                    let strong = word_attribute(attributes, "strong")
+                                        .and_then(|x| x.split(',').next()?.trim_start_matches(&['G', 'H'][..]).parse().ok());
+                                    let grammar = word_attribute(attributes, "x-morph")
+                                        .map(|x| String::from(x.split_once(':').map(|x| x.1).unwrap_or(x)));
+                                    let chunk = Chunk { strong: strong.map(|x| StrongNumber::new(x, grammar)), ..Chunk::new(normalize(text)) };
+                                    bible.get_verse_mut(b, c, v).add_strong(Chunk { red, ..chunk });
+                                },
+                                _ => match NOTES.iter().find(|(x, _)| *x == name) {
+                                    Some((x, kind)) => {
+                                        losses.add(kind, 1);
+                                        note = Some(x);
+                                    },
+                                    None => {
+                                        if HEADINGS.contains(&name) {
+                                            losses.add("headings", 1);
+                                        } else if is_introduction(name) {
+                                            losses.add("introductions", 1);
+                                        }
+                                        marker = name;
+                                    },
+                                },
+                            },
+                        }
+                        continue;
+                    },
+                    Token::Text(text) => text,
+                };
+                if note.is_some() {
+                    continue;
+                }
+                if let Some(word) = word.as_mut() {
+                    word.push_str(text);
+                    continue;
+                }
+                let text = match marker {
+                    "id" => {
+                        let mut parts = text.trim().splitn(2, char::is_whitespace);
+                        let code = parts.next().unwrap_or("").to_uppercase();
+                        book = USFM_BOOKS.iter().position(|x| *x == code);
+                        if book.is_none() {
+                            return Err(From::from(format!("{}: unknown book code '{}'.", file, code)));
+                        }
+                        if let Some(name) = parts.next().map(str::trim).filter(|x| !x.is_empty()) {
+                            bible.name = String::from(name);
+                        }
+                        (chapter, verse) = (None, None);
+                        ""
+                    },
+                    "rem" => {
+                        if let Some(identifier) = text.trim().strip_prefix("identifier:") {
+                            bible.identifier = String::from(identifier.trim());
+                        }
+                        ""
+                    },
+                    "c" => {
+                        chapter = text.split_whitespace().next().and_then(|x| x.parse::<usize>().ok()).and_then(|x| x.checked_sub(1));
+                        verse = None;
+                        if let (Some(b), Some(c)) = (book, chapter) {
+                            bible.get_chapter_mut(b, c);
+                        }
+                        ""
+                    },
+                    "v" => {
+                        let (number, rest) = text.trim_start().split_once(char::is_whitespace).unwrap_or((text.trim(), ""));
+                        let number: String = number.chars().take_while(|x| x.is_ascii_digit()).collect();
+                        verse = number.parse::<usize>().ok().and_then(|x| x.checked_sub(1));
+                        if let (Some(b), Some(c), Some(v)) = (book, chapter, verse) {
+                            bible.get_chapter_mut(b, c).add_verse(Verse::new(v));
+                        }
+                        rest
+                    },
+                    x if HEADINGS.contains(&x) || METADATA.contains(&x) || is_introduction(x) => "",
+                    _ => text,
+                };
+                marker = "";
+                if let (Some(b), Some(c), Some(v), false) = (book, chapter, verse, text.trim().is_empty()) {
+                    bible.get_verse_mut(b, c, v).add_strong(Chunk { red, ..Chunk::new(normalize(text)) });
+                }
+            }
+        }
+        if bible.identifier == "Unknown" {
+            // usfm has no identifier of the translation, use the name of the file or directory
+            let stem = Path::new(path.trim_end_matches('/')).file_stem().map(|x| x.to_string_lossy().to_uppercase());
+            bible.identifier = stem.unwrap_or_else(|| String::from("Unknown"));
+        }
+        bible.books.sort_by_key(|x| x.nr);
+        bible.index();
+        bible.content_hash = content_hash(files.iter().map(|x| x.1.as_str()).collect::<String>().as_bytes());
+        Ok(bible)
+    }
+}
+
+impl BibleExporter for UsfmBible {
+    /// Writes a file per book into the directory `path`, e.g. `43-JHN.usfm`.
+    fn write(bible: &Bible, path: &str, _losses: &mut Losses) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(path)?;
+        for book in bible.books.iter().filter(|x| x.nr < USFM_BOOKS.len()) {
+            let code = USFM_BOOKS[book.nr];
+            let mut out = String::new();
+            let _ = writeln!(out, "\\id {} {}\n\\usfm 3.0\n\\ide UTF-8\n\\rem identifier: {}", code, bible.name, bible.identifier);
+            let _ = writeln!(out, "\\h {0}\n\\toc1 {0}\n\\mt1 {0}", crate::BOOKS[book.nr]);
+            for chapter in &book.chapters {
+                let _ = writeln!(out, "\\c {}\n\\p", chapter.chapter + 1);
+                for verse in &chapter.verses {
+                    let _ = write!(out, "\\v {}", verse.verse + 1);
+                    let mut red = false;
+                    for chunk in &verse.chunks {
+                        if chunk.red != red {
+                            out.push_str(if chunk.red { " \\wj" } else { "\\wj*" });
+                            red = chunk.red;
+                        }
+                        out.push(' ');
+                        match &chunk.strong {
+                            Some(strong) => {
+                                // nested in \wj the word marker needs a +
+                                let marker = if red { "\\+w" } else { "\\w" };
+                                let _ = write!(out, "{} {}|strong=\"{}\"", marker, chunk.text, StrongKey::for_book(book.nr, strong.number));
+                                if let Some(grammar) = &strong.grammar {
+                                    let _ = write!(out, " x-morph=\"{}\"", grammar);
+                                }
+                                let _ = write!(out, "{}*", marker);
+                            },
+                            None => out.push_str(&chunk.text),
+                        }
+                    }
+                    if red {
+                        out.push_str("\\wj*");
+                    }
+                    out.push('\n');
+                }
+            }
+            std::fs::write(Path::new(path).join(format!("{:02}-{}.usfm", book.nr + 1, code)), out)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,334 @@
+use crate::{Bible, Chapter, Verse, Versification, BOOKS, format_reference};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+// Declarations
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The file cannot be parsed or its verses would be mixed up
+    Error,
+    /// The file can be served, but is probably incomplete or numbered unusually
+    Warning,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Short identifier of the check, e.g. `duplicate_verse`
+    pub kind: &'static str,
+    pub message: String,
+    /// 1-based position in the file, if the issue belongs to an element
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+/// The issues found in a bible file, in the order of the file.
+#[derive(Clone, Debug, Serialize)]
+pub struct Validation {
+    pub path: String,
+    pub identifier: Option<String>,
+    pub books: usize,
+    pub chapters: usize,
+    pub verses: usize,
+    /// The numbering the file follows, `None` if it could not be read completely
+    pub versification: Option<Versification>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<Issue>,
+}
+
+/// Collects the issues while reading, with the line and column of the current element.
+struct Checker<'a> {
+    /// Byte offsets of the line starts
+    lines: Vec<usize>,
+    text: &'a [u8],
+    issues: Vec<Issue>,
+}
+
+// Implementation
+impl<'a> Checker<'a> {
+    fn new(text: &'a [u8]) -> Self {
+        let lines = std::iter::once(0).chain(text.iter().enumerate().filter(|(_, &b)| b == b'\n').map(|(i, _)| i + 1)).collect();
+        Self { lines, text, issues: vec![] }
+    }
+
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.lines.partition_point(|&x| x <= offset).max(1);
+        let start = self.lines[line - 1];
+        let column = String::from_utf8_lossy(&self.text[start..offset.min(self.text.len())]).chars().count() + 1;
+        (line, column)
+    }
+
+    fn add(&mut self, severity: Severity, kind: &'static str, offset: Option<usize>, message: String) {
+        let (line, column) = match offset.map(|x| self.position(x)) {
+            Some((line, column)) => (Some(line), Some(column)),
+            None => (None, None),
+        };
+        self.issues.push(Issue { severity, kind, message, line, column });
+    }
+
+    fn error(&mut self, kind: &'static str, offset: Option<usize>, message: String) {
+        self.add(Severity::Error, kind, offset, message);
+    }
+
+    fn warning(&mut self, kind: &'static str, offset: Option<usize>, message: String) {
+        self.add(Severity::Warning, kind, offset, message);
+    }
+
+    /// The 1-based number attribute of an element, reported if it is missing or no number.
+    fn number(&mut self, e: &BytesStart, reader: &Reader<&[u8]>, name: &str, offset: usize) -> Option<usize> {
+        let element = String::from_utf8_lossy(e.name()).into_owned();
+        match attribute(e, reader, name.as_bytes()) {
+            None => self.error("missing_number", Some(offset), format!("{} without {} attribute.", element, name)),
+            Some(value) => match value.trim().parse::<usize>() {
+                Ok(number) if number > 0 => return Some(number),
+                _ => self.error("invalid_number", Some(offset), format!("{} has the invalid {} '{}'.", element, name, value)),
+            },
+        }
+        None
+    }
+}
+
+fn attribute(e: &BytesStart, reader: &Reader<&[u8]>, name: &[u8]) -> Option<String> {
+    e.attributes().flatten()
+        .find(|x| x.key == name)
+        .map(|x| x.unescape_and_decode_value(reader).unwrap_or_else(|_| String::from_utf8_lossy(&x.value).into_owned()))
+}
+
+/// Ranges of the numbers missing from `1..=max`, e.g. `3-5` and `9`.
+fn gaps(numbers: &BTreeSet<usize>) -> Vec<String> {
+    let mut gaps = vec![];
+    let mut expected = 1;
+    for &number in numbers {
+        match number - expected {
+            0 => {},
+            1 => gaps.push(expected.to_string()),
+            _ => gaps.push(format!("{}-{}", expected, number - 1)),
+        }
+        expected = number + 1;
+    }
+    gaps
+}
+
+impl Validation {
+    pub fn is_valid(&self) -> bool {
+        self.errors == 0
+    }
+}
+
+/// Checks a Zefania XML file for the problems which make parsing fail or mix up verses,
+/// and for gaps, empty verses and an unusual numbering. The versification of the books
+/// is compared to `scheme` if given, the number of chapters to the detected one otherwise.
+pub fn validate(path: &str, scheme: Option<&Versification>) -> Validation {
+    let mut validation = Validation {
+        path: String::from(path), identifier: None, books: 0, chapters: 0, verses: 0, versification: None,
+        errors: 0, warnings: 0, issues: vec![],
+    };
+    let text = match std::fs::read(path) {
+        Ok(text) => text,
+        Err(e) => {
+            validation.issues.push(Issue { severity: Severity::Error, kind: "io", message: e.to_string(), line: None, column: None });
+            validation.errors = 1;
+            return validation;
+        },
+    };
+    let mut checker = Checker::new(&text);
+    let mut reader = Reader::from_reader(text.as_slice());
+    let strong_regex = Regex::new(r"^[GgHh]?\d{1,5}$").unwrap();
+    let rmac_regex = Regex::new(r"^[A-Za-z0-9]+(-[A-Za-z0-9]+)*$").unwrap();
+
+    // the numbers found so far, only the first book, chapter or verse of a number counts
+    let mut bible = Bible::new("Unknown", "Unknown translation");
+    let (mut book, mut chapter, mut verse): (Option<usize>, Option<usize>, Option<usize>) = (None, None, None);
+    // the open elements, to report a CHAPTER or VERS only if it has no parent at all
+    let mut elements: Vec<Vec<u8>> = vec![];
+    let mut verse_start = 0;
+    let mut verse_text = false;
+    let mut identifier_content = false;
+    let mut complete = false;
+    let mut buf = Vec::new();
+    loop {
+        let offset = reader.buffer_position();
+        let event = match reader.read_event(&mut buf) {
+            Ok(event) => event,
+            Err(e) => {
+                checker.error("xml", Some(reader.buffer_position()), e.to_string());
+                break;
+            },
+        };
+        let (e, empty) = match event {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::Text(e) => {
+                let text = e.unescape_and_decode(&reader).unwrap_or_default();
+                if identifier_content {
+                    validation.identifier = Some(String::from(text.trim()));
+                }
+                verse_text |= verse.is_some() && !text.trim().is_empty();
+                buf.clear();
+                continue;
+            },
+            Event::End(e) => {
+                elements.pop();
+                match e.name() {
+                    b"identifier" => identifier_content = false,
+                    b"BIBLEBOOK" => book = None,
+                    b"CHAPTER" => chapter = None,
+                    b"VERS" => {
+                        if let (Some(b), Some(c), Some(v), false) = (book, chapter, verse, verse_text) {
+                            checker.warning("empty_verse", Some(verse_start), format!("{} is empty.", format_reference(b, c, v)));
+                        }
+                        verse = None;
+                    },
+                    _ => {},
+                }
+                buf.clear();
+                continue;
+            },
+            Event::Eof => {
+                complete = true;
+                break;
+            },
+            _ => {
+                buf.clear();
+                continue;
+            },
+        };
+        match e.name() {
+            b"identifier" => identifier_content = !empty,
+            b"BIBLEBOOK" => {
+                book = None;
+                if let Some(number) = checker.number(&e, &reader, "bnumber", offset) {
+                    if number > BOOKS.len() {
+                        checker.error("unknown_book", Some(offset), format!("Unknown book number {}, expected 1 to {}.", number, BOOKS.len()));
+                    } else if bible.books.iter().any(|x| x.nr == number - 1) {
+                        checker.error("duplicate_book", Some(offset), format!("{} occurs twice.", BOOKS[number - 1]));
+                    } else {
+                        bible.add_book(number - 1);
+                        book = Some(number - 1);
+                    }
+                }
+            },
+            b"CHAPTER" => {
+                chapter = None;
+                let number = checker.number(&e, &reader, "cnumber", offset);
+                match (book, number) {
+                    (None, _) if !elements.iter().any(|x| x == b"BIBLEBOOK") =>
+                        checker.error("misplaced", Some(offset), String::from("CHAPTER outside of a BIBLEBOOK.")),
+                    (Some(b), Some(number)) => {
+                        let chapters = &mut bible.get_book_mut(b).chapters;
+                        if chapters.iter().any(|x| x.chapter == number - 1) {
+                            checker.error("duplicate_chapter", Some(offset), format!("{} {} occurs twice.", BOOKS[b], number));
+                        } else {
+                            chapters.push(Chapter::new(number - 1));
+                            chapter = Some(number - 1);
+                        }
+                    },
+                    _ => {},
+                }
+            },
+            b"VERS" => {
+                verse = None;
+                let number = checker.number(&e, &reader, "vnumber", offset);
+                match (book, chapter, number) {
+                    (Some(b), Some(c), Some(number)) => {
+                        let reference = format_reference(b, c, number - 1);
+                        let verses = &mut bible.get_chapter_mut(b, c).verses;
+                        if verses.iter().any(|x| x.verse == number - 1) {
+                            checker.error("duplicate_verse", Some(offset), format!("{} occurs twice.", reference));
+                        } else {
+                            verses.push(Verse::new(number - 1));
+                            if empty {
+                                checker.warning("empty_verse", Some(offset), format!("{} is empty.", reference));
+                            } else {
+                                verse = Some(number - 1);
+                                verse_start = offset;
+                                verse_text = false;
+                            }
+                        }
+                    },
+                    _ if !elements.iter().any(|x| x == b"CHAPTER") =>
+                        checker.error("misplaced", Some(offset), String::from("VERS outside of a CHAPTER.")),
+                    _ => {},
+                }
+            },
+            b"gr" => {
+                if let Some(value) = attribute(&e, &reader, b"str") {
+                    // several numbers of one word are separated by spaces
+                    for number in value.split_whitespace().filter(|x| !strong_regex.is_match(x)) {
+                        checker.error("malformed_strong", Some(offset), format!("Malformed strong number '{}'.", number));
+                    }
+                    if value.trim().is_empty() {
+                        checker.error("malformed_strong", Some(offset), String::from("Empty str attribute."));
+                    }
+                }
+                if let Some(value) = attribute(&e, &reader, b"rmac") {
+                    if !value.is_empty() && !rmac_regex.is_match(&value) {
+                        checker.warning("malformed_rmac", Some(offset), format!("Malformed grammar code '{}'.", value));
+                    }
+                }
+            },
+            _ => {},
+        }
+        if empty {
+            match e.name() {
+                b"BIBLEBOOK" => book = None,
+                b"CHAPTER" => chapter = None,
+                _ => {},
+            }
+        } else {
+            elements.push(e.name().to_vec());
+        }
+        buf.clear();
+    }
+
+    if complete {
+        if validation.identifier.as_deref().unwrap_or("").is_empty() {
+            checker.warning("missing_identifier", None, String::from("No identifier element, the bible cannot be told apart from others."));
+        }
+        for b in &bible.books {
+            let numbers: BTreeSet<usize> = b.chapters.iter().map(|x| x.chapter + 1).collect();
+            for gap in gaps(&numbers) {
+                checker.error("missing_chapter", None, format!("{} {} is missing.", BOOKS[b.nr], gap));
+            }
+            for c in &b.chapters {
+                let numbers: BTreeSet<usize> = c.verses.iter().map(|x| x.verse + 1).collect();
+                for gap in gaps(&numbers) {
+                    checker.warning("verse_gap", None, format!("{} {},{} is missing.", BOOKS[b.nr], c.chapter + 1, gap));
+                }
+            }
+        }
+        // the chapter lookups rely on sorted chapters and verses
+        for b in bible.books.iter_mut() {
+            b.chapters.sort_by_key(|x| x.chapter);
+            b.chapters.iter_mut().for_each(|x| x.verses.sort_by_key(|x| x.verse));
+        }
+        let detected = Versification::detect(&bible);
+        for b in &bible.books {
+            let scheme = scheme.unwrap_or(&detected);
+            if scheme.is_hebrew(b.nr) != detected.is_hebrew(b.nr) {
+                let (found, expected) = if detected.is_hebrew(b.nr) { ("hebrew", "english") } else { ("english", "hebrew") };
+                checker.warning("versification", None, format!("{} follows the {} numbering, expected the {} one.", BOOKS[b.nr], found, expected));
+                continue;
+            }
+            let chapters = b.chapters.iter().map(|x| x.chapter + 1).max().unwrap_or(0);
+            if let Some(expected) = scheme.chapters(b.nr).filter(|&x| x != chapters) {
+                checker.warning("chapter_count", None, format!("{} has {} chapters, expected {}.", BOOKS[b.nr], chapters, expected));
+            }
+        }
+        validation.versification = Some(detected);
+    }
+    validation.books = bible.books.len();
+    validation.chapters = bible.books.iter().map(|x| x.chapters.len()).sum();
+    validation.verses = bible.books.iter().flat_map(|x| x.chapters.iter()).map(|x| x.verses.len()).sum();
+    validation.errors = checker.issues.iter().filter(|x| x.severity == Severity::Error).count();
+    validation.warnings = checker.issues.len() - validation.errors;
+    validation.issues = checker.issues;
+    validation
+}
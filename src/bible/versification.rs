@@ -0,0 +1,160 @@
+use crate::Bible;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+// Declarations
+/// Verse numbering of a translation. The english numbering (as in the King James
+/// Version) is the standard all others are mapped to; books listed in `hebrew_books`
+/// follow the numbering of the hebrew (masoretic) text instead.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Versification {
+    pub hebrew_books: Vec<usize>,
+}
+
+/// Hebrew verses `chapter:start-end` which are `std_chapter:std_start-...` in the english numbering (1-based).
+struct VerseMapping {
+    chapter: usize,
+    start: usize,
+    end: usize,
+    std_chapter: usize,
+    std_start: usize,
+}
+
+/// The known differences of a book. A book follows the hebrew numbering if the
+/// probe chapter has the hebrew number of verses.
+struct BookVersification {
+    book: usize,
+    probe_chapter: usize,
+    probe_hebrew_verses: usize,
+    mappings: &'static [VerseMapping],
+}
+
+const fn map(chapter: usize, start: usize, end: usize, std_chapter: usize, std_start: usize) -> VerseMapping {
+    VerseMapping { chapter, start, end, std_chapter, std_start }
+}
+
+static HEBREW_VERSIFICATION: &[BookVersification] = &[
+    BookVersification { book: 0, probe_chapter: 31, probe_hebrew_verses: 54, mappings: &[
+        map(32, 1, 1, 31, 55), map(32, 2, 33, 32, 1),
+    ] },
+    BookVersification { book: 1, probe_chapter: 7, probe_hebrew_verses: 29, mappings: &[
+        map(7, 26, 29, 8, 1), map(8, 1, 28, 8, 5), map(21, 37, 37, 22, 1), map(22, 1, 30, 22, 2),
+    ] },
+    BookVersification { book: 2, probe_chapter: 5, probe_hebrew_verses: 26, mappings: &[
+        map(5, 20, 26, 6, 1), map(6, 1, 23, 6, 8),
+    ] },
+    BookVersification { book: 3, probe_chapter: 16, probe_hebrew_verses: 35, mappings: &[
+        map(17, 1, 15, 16, 36), map(17, 16, 28, 17, 1), map(30, 1, 1, 29, 40), map(30, 2, 17, 30, 1),
+    ] },
+    BookVersification { book: 4, probe_chapter: 12, probe_hebrew_verses: 31, mappings: &[
+        map(13, 1, 1, 12, 32), map(13, 2, 19, 13, 1), map(23, 1, 1, 22, 30), map(23, 2, 26, 23, 1),
+    ] },
+    BookVersification { book: 10, probe_chapter: 4, probe_hebrew_verses: 20, mappings: &[
+        map(5, 1, 14, 4, 21), map(5, 15, 32, 5, 1),
+    ] },
+    BookVersification { book: 28, probe_chapter: 2, probe_hebrew_verses: 27, mappings: &[
+        map(3, 1, 5, 2, 28), map(4, 1, 21, 3, 1),
+    ] },
+    BookVersification { book: 38, probe_chapter: 3, probe_hebrew_verses: 24, mappings: &[
+        map(3, 19, 24, 4, 1),
+    ] },
+];
+
+/// Number of chapters of each book in the english numbering.
+static ENGLISH_CHAPTERS: [usize; 66] = [
+    50, 40, 27, 36, 34, 24, 21, 4, 31, 24, 22, 25, 29, 36, 10, 13, 10, 42, 150, 31, 12, 8, 66, 52, 5, 48, 12, 14, 3, 9, 1, 4, 7, 3, 3, 3, 2, 14, 4,
+    28, 16, 24, 21, 28, 16, 16, 13, 6, 6, 4, 4, 5, 3, 6, 4, 3, 1, 13, 5, 5, 3, 5, 1, 1, 1, 22,
+];
+
+// Implementation
+impl Versification {
+    pub fn english() -> Self {
+        Self { hebrew_books: vec![] }
+    }
+
+    pub fn hebrew() -> Self {
+        Self { hebrew_books: HEBREW_VERSIFICATION.iter().map(|x| x.book).collect() }
+    }
+
+    /// Detects for each book with known differences which numbering the bible follows.
+    pub fn detect(bible: &Bible) -> Self {
+        let hebrew_books = HEBREW_VERSIFICATION.iter()
+            .filter(|x| bible.get_chapter(x.book, x.probe_chapter - 1)
+                .is_some_and(|chapter| chapter.verses.len() == x.probe_hebrew_verses))
+            .map(|x| x.book)
+            .collect();
+        Self { hebrew_books }
+    }
+
+    fn mappings(&self, book: usize) -> &'static [VerseMapping] {
+        match HEBREW_VERSIFICATION.iter().find(|x| x.book == book) {
+            Some(x) if self.hebrew_books.contains(&book) => x.mappings,
+            _ => &[],
+        }
+    }
+
+    /// Whether the book follows the hebrew numbering.
+    pub fn is_hebrew(&self, book: usize) -> bool {
+        self.hebrew_books.contains(&book)
+    }
+
+    /// The number of chapters of the book in this numbering, e.g. 4 for Joel in the hebrew one.
+    pub fn chapters(&self, book: usize) -> Option<usize> {
+        let chapters = *ENGLISH_CHAPTERS.get(book)?;
+        Some(self.from_standard(book, chapters - 1, 0).1 + 1)
+    }
+
+    /// Maps a 0-based verse of this numbering to the english numbering.
+    pub fn to_standard(&self, book: usize, chapter: usize, verse: usize) -> (usize, usize, usize) {
+        let (chapter, verse) = (chapter + 1, verse + 1);
+        match self.mappings(book).iter().find(|x| x.chapter == chapter && x.start <= verse && verse <= x.end) {
+            Some(x) => (book, x.std_chapter - 1, x.std_start + verse - x.start - 1),
+            None => (book, chapter - 1, verse - 1),
+        }
+    }
+
+    /// Maps a 0-based verse of the english numbering to this numbering.
+    pub fn from_standard(&self, book: usize, chapter: usize, verse: usize) -> (usize, usize, usize) {
+        let (chapter, verse) = (chapter + 1, verse + 1);
+        let found = self.mappings(book).iter()
+            .find(|x| x.std_chapter == chapter && x.std_start <= verse && verse <= x.std_start + x.end - x.start);
+        match found {
+            Some(x) => (book, x.chapter - 1, x.start + verse - x.std_start - 1),
+            None => (book, chapter - 1, verse - 1),
+        }
+    }
+}
+
+impl fmt::Display for Versification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.hebrew_books.is_empty() {
+            write!(f, "english")
+        } else if *self == Self::hebrew() {
+            write!(f, "hebrew")
+        } else {
+            write!(f, "mixed")
+        }
+    }
+}
+
+impl FromStr for Versification {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "english" | "kjv" => Ok(Self::english()),
+            "hebrew" => Ok(Self::hebrew()),
+            _ => Err(format!("Unknown versification '{}'.", s)),
+        }
+    }
+}
+
+impl Serialize for Versification {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
@@ -1,5 +1,5 @@
-use crate::{Bible, Verse, Chunk, VerseRef};
-use crate::BibleParser;
+use crate::{Bible, Verse, Chunk, VerseRef, Versification, Losses, content_hash, render_zefania};
+use crate::{BibleExporter, BibleParser};
 
 use std::str::{self, FromStr};
 use std::error::Error;
@@ -17,9 +17,29 @@ fn get_attribute<T>(e: &BytesStart, attr_name: &[u8]) -> T where T: FromStr + De
     }
 }
 
+/// Elements whose content is no verse text, with the kind of loss they count as.
+static SKIPPED: &[(&[u8], &str)] = &[
+    (b"NOTE", "notes"), (b"REMARK", "notes"), (b"XREF", "cross references"), (b"CAPTION", "headings"), (b"PROLOG", "introductions"),
+];
+
+fn skipped_kind(name: &[u8]) -> Option<&'static str> {
+    SKIPPED.iter().find(|(x, _)| *x == name).map(|x| x.1)
+}
+
+/// Whether a `STYLE` element colors its text red, as used for the words of Jesus.
+fn is_red_style(e: &BytesStart) -> bool {
+    let css: String = get_attribute(e, b"css");
+    let css = css.to_lowercase().replace(' ', "");
+    ["color:#ff0000", "color:#f00", "color:red"].iter().any(|x| css.contains(x))
+}
+
 impl BibleParser for ZefaniaBible {
     fn parse(path: &str) -> Result<Self, Box<dyn Error>> {
-        println!("Parse translation ...");
+        Self::parse_with_losses(path, &mut Losses::default())
+    }
+
+    fn parse_with_losses(path: &str, losses: &mut Losses) -> Result<Self, Box<dyn Error>> {
+        log::info!("Parse translation {} ...", path);
         let mut parser = Reader::from_file(path)?;
         let (mut _count, mut bnumber, mut cnumber, mut vnumber, mut depth, mut strong_number, mut grammar) =
             (0, 0, 0, 0, 0, -1, String::new());
@@ -27,9 +47,16 @@ impl BibleParser for ZefaniaBible {
         let mut buf = Vec::new();
         let mut bible = Bible::new("Unknown", "Unknown translation");
         let mut title_content = false;
+        // whether each open STYLE element is red
+        let mut styles: Vec<bool> = vec![];
         let mut identifier_content = false;
+        // nesting of the open skipped elements
+        let mut skipped = 0;
         loop {
             match parser.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) if skipped > 0 && skipped_kind(e.name()).is_some() => skipped += 1,
+                Ok(Event::End(ref e)) if skipped > 0 && skipped_kind(e.name()).is_some() => skipped -= 1,
+                Ok(Event::Start(_)) | Ok(Event::End(_)) | Ok(Event::Empty(_)) | Ok(Event::Text(_)) if skipped > 0 => (),
                 Ok(Event::Start(ref e)) => {
                     match e.name() {
                         b"title" => {
@@ -65,9 +92,16 @@ impl BibleParser for ZefaniaBible {
                             strong_number = caps["strong"].parse::<i32>().unwrap();
                             grammar = get_attribute(e, b"rmac");
                         },
-                        _ => (),
+                        b"STYLE" => styles.push(is_red_style(e)),
+                        name => if let Some(kind) = skipped_kind(name) {
+                            losses.add(kind, 1);
+                            skipped = 1;
+                        },
                     }
                 },
+                Ok(Event::Empty(ref e)) => if let Some(kind) = skipped_kind(e.name()) {
+                    losses.add(kind, 1);
+                },
 
                 Ok(Event::Text(e)) => {
                     if title_content {
@@ -88,10 +122,9 @@ impl BibleParser for ZefaniaBible {
                                 } else {
                                     None
                                 };
+                                let chunk = Chunk::new_strong(String::from(text), strong_number, grammar_option);
                                 bible.get_verse_mut(bnumber, cnumber, vnumber)
-                                    .add_strong(Chunk::new_strong(
-                                        String::from(text),
-                                        strong_number, grammar_option));
+                                    .add_strong(Chunk { red: styles.contains(&true), ..chunk });
                                 bible.insert_strong_variant(strong_number, String::from(text), VerseRef::new(bnumber, cnumber, vnumber));
                                 // if let Some(v) = bible.get_verse(bnumber, cnumber, vnumber) {
                                 //     let mut chunks: Vec<Chunk> = vec![];
@@ -101,8 +134,9 @@ impl BibleParser for ZefaniaBible {
                                 //     bible.insert_strong_variant(strong_number, String::from(text), VerseRef::new_with_chunks(bnumber, cnumber, vnumber, chunks));
                                 // }
                             } else {
+                                let chunk = Chunk::new(String::from(text));
                                 bible.get_verse_mut(bnumber, cnumber, vnumber)
-                                    .add_chunk(String::from(e.unescape_and_decode(&parser).unwrap().trim()));
+                                    .add_strong(Chunk { red: styles.contains(&true), ..chunk });
                             }
                             strong_number = -1;
                         }
@@ -110,6 +144,7 @@ impl BibleParser for ZefaniaBible {
                 },
                 Ok(Event::End(e)) => match e.name() {
                     b"gr" => depth -= 1,
+                    b"STYLE" => { styles.pop(); },
                     _ => ()
                 }
                 Ok(Event::Eof) => break, // exits the loop when reaching end of file
@@ -119,8 +154,17 @@ impl BibleParser for ZefaniaBible {
             buf.clear();
         }
 
-        println!("  ... done.");
+        bible.versification = Versification::detect(&bible);
+        bible.content_hash = content_hash(&std::fs::read(path)?);
+        log::info!("  ... done.");
 
         Ok(bible)
     }
 }
+
+impl BibleExporter for ZefaniaBible {
+    fn write(bible: &Bible, path: &str, _losses: &mut Losses) -> Result<(), Box<dyn Error>> {
+        std::fs::write(path, render_zefania(bible))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use actix_web::http::Method;
+use actix_web::{web, Error, HttpResponse};
+use bible::content_hash;
+use std::collections::HashMap;
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::str::FromStr;
+use crate::auth::ApiKeys;
+use crate::registry::BibleRegistry;
+
+/// Groups of api routes sharing the same `Cache-Control` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RouteClass {
+    /// Translations and their info, which change when the bibles are reloaded
+    Meta,
+    /// Chapters, verses, passages and comparisons
+    Text,
+    Search,
+    Strongs,
+    /// Statistics and related strong numbers
+    Analysis,
+}
+
+/// The `Cache-Control` header of each route class.
+#[derive(Clone)]
+pub struct CachePolicy {
+    pub cache_control: HashMap<RouteClass, String>,
+}
+
+impl FromStr for RouteClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "meta" => Ok(Self::Meta),
+            "text" => Ok(Self::Text),
+            "search" => Ok(Self::Search),
+            "strongs" => Ok(Self::Strongs),
+            "analysis" => Ok(Self::Analysis),
+            _ => Err(format!("Unknown route class '{}', expected meta, text, search, strongs or analysis.", s)),
+        }
+    }
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        let cache_control = [
+            (RouteClass::Meta, "no-cache"),
+            (RouteClass::Text, "public, max-age=86400"),
+            (RouteClass::Search, "public, max-age=3600"),
+            (RouteClass::Strongs, "public, max-age=86400"),
+            (RouteClass::Analysis, "public, max-age=86400"),
+        ].iter().map(|(class, value)| (*class, String::from(*value))).collect();
+        Self { cache_control }
+    }
+}
+
+impl CachePolicy {
+    /// Overrides the header of a route class with a `class=value` setting, e.g. `search=no-store`.
+    pub fn set(&mut self, setting: &str) -> Result<(), String> {
+        let (class, value) = setting.split_once('=')
+            .ok_or_else(|| format!("Invalid cache setting '{}', expected class=value.", setting))?;
+        HeaderValue::from_str(value.trim()).map_err(|_| format!("Invalid Cache-Control value '{}'.", value))?;
+        self.cache_control.insert(class.parse()?, String::from(value.trim()));
+        Ok(())
+    }
+}
+
+/// Answers GET requests with an `ETag` and the `Cache-Control` of the route class,
+/// and conditional requests matching the `ETag` with 304 Not Modified without
+/// running the handler. The `ETag` is derived from the translation identifier, the
+/// content hash of its file, the request uri and `Accept` header, so it changes only when the bible
+/// is reloaded with a different content.
+pub struct HttpCache {
+    class: RouteClass,
+}
+
+impl HttpCache {
+    pub fn new(class: RouteClass) -> Self {
+        Self { class }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for HttpCache
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = HttpCacheMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(HttpCacheMiddleware { service: Rc::new(service), class: self.class }))
+    }
+}
+
+pub struct HttpCacheMiddleware<S> {
+    service: Rc<S>,
+    class: RouteClass,
+}
+
+/// The `ETag` of a request, `None` if the translation is unknown.
+fn etag(req: &ServiceRequest) -> Option<String> {
+    let registry = req.app_data::<web::Data<BibleRegistry>>()?;
+    let (name, hash) = match req.match_info().get("identifier") {
+        Some(identifier) => (String::from(identifier), registry.get(identifier)?.content_hash),
+        None => {
+            // routes over all translations depend on every loaded bible
+            let mut bibles: Vec<(String, u64)> = registry.snapshot().values().map(|x| (x.identifier.clone(), x.content_hash)).collect();
+            bibles.sort();
+            (String::from("all"), content_hash(format!("{:?}", bibles).as_bytes()))
+        }
+    };
+    let uri = req.uri().path_and_query().map(|x| x.as_str()).unwrap_or("");
+    // the text routes answer in the format of the Accept header
+    let accept = req.headers().get(header::ACCEPT).map(|x| x.as_bytes()).unwrap_or(b"");
+    Some(format!("W/\"{}-{:016x}-{:016x}\"", name, hash, content_hash(&[uri.as_bytes(), b"\n", accept].concat())))
+}
+
+/// Weak comparison of the `If-None-Match` header with an `ETag`.
+fn matches_etag(req: &ServiceRequest, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    req.headers().get_all(header::IF_NONE_MATCH)
+        .filter_map(|x| x.to_str().ok())
+        .flat_map(|x| x.split(','))
+        .map(|x| x.trim())
+        .any(|x| x == "*" || x.trim_start_matches("W/") == etag)
+}
+
+/// The `Cache-Control` of a route class protected by api keys: `private` instead of
+/// `public`, so shared caches do not answer anonymous clients with protected content.
+fn private_cache_control(value: &str) -> String {
+    let mut directives: Vec<&str> = value.split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.eq_ignore_ascii_case("public"))
+        .collect();
+    if !directives.iter().any(|x| x.eq_ignore_ascii_case("private") || x.eq_ignore_ascii_case("no-store")) {
+        directives.insert(0, "private");
+    }
+    directives.join(", ")
+}
+
+fn set_cache_headers(headers: &mut HeaderMap, etag: &str, cache_control: Option<&str>, protected: bool) {
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        headers.insert(header::ETAG, value);
+    }
+    if let Some(value) = cache_control.and_then(|x| HeaderValue::from_str(x).ok()) {
+        headers.insert(header::CACHE_CONTROL, value);
+    }
+    // the ETag depends on the Accept header, which a 304 has to tell like the 200 it stands for
+    add_vary(headers, "Accept");
+    if protected {
+        add_vary(headers, "Authorization");
+        add_vary(headers, "X-API-Key");
+    }
+}
+
+/// Adds a header name to the `Vary` header, as a single header because the CORS
+/// middleware keeps only the first one when adding its own.
+fn add_vary(headers: &mut HeaderMap, name: &str) {
+    let mut names: Vec<String> = headers.get_all(header::VARY)
+        .filter_map(|x| x.to_str().ok())
+        .flat_map(|x| x.split(','))
+        .map(|x| String::from(x.trim()))
+        .filter(|x| !x.is_empty())
+        .collect();
+    if names.iter().any(|x| x.eq_ignore_ascii_case(name)) {
+        return;
+    }
+    names.push(String::from(name));
+    if let Ok(value) = HeaderValue::from_str(&names.join(", ")) {
+        headers.insert(header::VARY, value);
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for HttpCacheMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let cacheable = req.method() == Method::GET || req.method() == Method::HEAD;
+        let etag = if cacheable { etag(&req) } else { None };
+        let protected = req.app_data::<web::Data<ApiKeys>>().is_some_and(|x| x.protects(self.class.into()));
+        let cache_control = req.app_data::<web::Data<CachePolicy>>()
+            .and_then(|x| x.cache_control.get(&self.class).cloned())
+            .map(|x| if protected { private_cache_control(&x) } else { x });
+
+        Box::pin(async move {
+            let etag = match etag {
+                Some(etag) => etag,
+                None => return Ok(service.call(req).await?.map_into_left_body()),
+            };
+            if matches_etag(&req, &etag) {
+                let mut res = HttpResponse::NotModified().finish();
+                set_cache_headers(res.headers_mut(), &etag, cache_control.as_deref(), protected);
+                return Ok(req.into_response(res).map_into_right_body());
+            }
+            let mut res = service.call(req).await?;
+            if res.status().is_success() {
+                set_cache_headers(res.headers_mut(), &etag, cache_control.as_deref(), protected);
+            }
+            Ok(res.map_into_left_body())
+        })
+    }
+}
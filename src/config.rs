@@ -0,0 +1,385 @@
+use actix_web::http::header::HeaderValue;
+use bible::Versification;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::net::ToSocketAddrs;
+use std::path::{Path, PathBuf};
+use crate::auth::{ApiKeys, Scope};
+use crate::cache::CachePolicy;
+use crate::routes::{ApiLimits, DEFAULT_MAX_PAGE_SIZE, DEFAULT_MAX_PASSAGE_VERSES, DEFAULT_MAX_SEARCH_COST};
+
+/// Settings of `serve`, read from a yaml or toml file given with `--config`.
+/// Settings are taken from the defaults, the file, the `BIBLE_CLI_*` environment
+/// variables and the command line options, later ones overriding earlier ones.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Address to bind to, e.g. `127.0.0.1` or `::`
+    pub bind: String,
+    pub port: u16,
+    /// Folder of the bible XML files
+    pub folder: PathBuf,
+    #[serde(rename = "static")]
+    pub static_files: StaticConfig,
+    pub cors: CorsConfig,
+    /// Serve https instead of http
+    pub tls: Option<TlsConfig>,
+    /// Require api keys for some route groups
+    pub auth: Option<AuthConfig>,
+    /// log4rs configuration file, the embedded configuration is used if missing
+    pub log: Option<PathBuf>,
+    /// Maximum size of json request bodies in bytes
+    pub json_limit: usize,
+    pub max_passage_verses: usize,
+    /// Maximum number of verses or references in a page of search results or strong references
+    pub max_page_size: usize,
+    /// Maximum estimated cost of a search, see `SearchQuery::estimated_cost`
+    pub max_search_cost: usize,
+    /// Limit the requests of each client
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Check the folder for changed bibles every given seconds
+    pub watch: Option<u64>,
+    pub admin: bool,
+    pub api_docs: bool,
+    /// Serve the Prometheus metrics at `/metrics`
+    pub metrics: bool,
+    pub graphql: GraphqlConfig,
+    pub cache: CacheConfig,
+    /// Options of the translations by identifier
+    pub translations: HashMap<String, TranslationConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StaticConfig {
+    /// Page served for the routes of the single page app
+    pub index: PathBuf,
+    /// Folder served at `/build/`
+    pub assets: PathBuf,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins allowed to call the api, `*` allows any origin
+    pub origins: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM file of the certificate chain
+    pub certificate: PathBuf,
+    /// PEM file of the private key, in PKCS#8, RSA or EC format
+    pub key: PathBuf,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// yaml or toml file of the api keys
+    pub keys: PathBuf,
+    /// Route groups which require a key
+    pub protect: Vec<Scope>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Tokens refilled per second, a request takes one token, searches and statistics more
+    pub rate: f64,
+    /// Maximum number of tokens of a client
+    pub burst: f64,
+    /// Identify anonymous clients by the `Forwarded` or `X-Forwarded-For` header of a proxy
+    pub trust_forwarded: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraphqlConfig {
+    /// Serve the GraphQL api at `/graphql`
+    pub enabled: bool,
+    /// Maximum nesting of the fields of a query
+    pub max_depth: usize,
+    /// Maximum complexity of a query, list fields count their estimated length times their fields
+    pub max_complexity: usize,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub compress: bool,
+    /// `Cache-Control` header by route class, e.g. `search: no-store`
+    pub cache_control: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TranslationConfig {
+    /// Display name overriding the title of the XML file
+    pub name: Option<String>,
+    /// `english` or `hebrew`, overriding the detected versification
+    pub versification: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: String::from("0.0.0.0"),
+            port: 8000,
+            folder: PathBuf::from("./bibles"),
+            static_files: StaticConfig::default(),
+            cors: CorsConfig::default(),
+            tls: None,
+            auth: None,
+            log: None,
+            json_limit: 4096,
+            max_passage_verses: DEFAULT_MAX_PASSAGE_VERSES,
+            max_page_size: DEFAULT_MAX_PAGE_SIZE,
+            max_search_cost: DEFAULT_MAX_SEARCH_COST,
+            rate_limit: None,
+            watch: None,
+            admin: false,
+            api_docs: true,
+            metrics: true,
+            graphql: GraphqlConfig::default(),
+            cache: CacheConfig::default(),
+            translations: HashMap::new(),
+        }
+    }
+}
+
+impl Default for StaticConfig {
+    fn default() -> Self {
+        Self { index: PathBuf::from("./static/index.html"), assets: PathBuf::from("./static/build") }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self { keys: PathBuf::new(), protect: vec![Scope::Read, Scope::Search, Scope::Admin] }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self { rate: 5.0, burst: 60.0, trust_forwarded: false }
+    }
+}
+
+impl Default for GraphqlConfig {
+    fn default() -> Self {
+        Self { enabled: true, max_depth: 12, max_complexity: 20000 }
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self { origins: vec![String::from("*")] }
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    env::var(format!("BIBLE_CLI_{}", name)).ok().filter(|x| !x.trim().is_empty())
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str) -> Result<Option<T>, String> {
+    env_var(name).map(|x| x.trim().parse().map_err(|_| format!("BIBLE_CLI_{}: invalid value '{}'", name, x))).transpose()
+}
+
+/// Reads a yaml or toml file, the format is chosen by the extension `.toml`, `.yaml` or `.yml`.
+pub fn read_file<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("toml") => toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e)),
+        Some("yaml") | Some("yml") => serde_yaml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e)),
+        _ => Err(format!("{}: unknown file format, expected a .toml, .yaml or .yml file", path.display())),
+    }
+}
+
+impl TlsConfig {
+    /// The rustls configuration with the certificate chain and private key.
+    pub fn server_config(&self) -> Result<rustls::ServerConfig, String> {
+        let open = |path: &Path| File::open(path).map(BufReader::new).map_err(|e| format!("{}: {}", path.display(), e));
+        let certificates: Vec<rustls::Certificate> = rustls_pemfile::certs(&mut open(&self.certificate)?)
+            .map_err(|e| format!("{}: {}", self.certificate.display(), e))?
+            .into_iter().map(rustls::Certificate).collect();
+        if certificates.is_empty() {
+            return Err(format!("{}: contains no certificate", self.certificate.display()));
+        }
+        let key = rustls_pemfile::read_all(&mut open(&self.key)?)
+            .map_err(|e| format!("{}: {}", self.key.display(), e))?
+            .into_iter()
+            .find_map(|item| match item {
+                rustls_pemfile::Item::PKCS8Key(key) | rustls_pemfile::Item::RSAKey(key) | rustls_pemfile::Item::ECKey(key) => Some(rustls::PrivateKey(key)),
+                _ => None,
+            })
+            .ok_or_else(|| format!("{}: contains no private key", self.key.display()))?;
+        rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(certificates, key)
+            .map_err(|e| format!("{}: {}", self.certificate.display(), e))
+    }
+}
+
+impl TranslationConfig {
+    pub fn versification(&self) -> Option<Versification> {
+        self.versification.as_ref().and_then(|x| x.parse().ok())
+    }
+}
+
+impl ServerConfig {
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        read_file(path)
+    }
+
+    /// Overrides the settings with the `BIBLE_CLI_*` environment variables.
+    pub fn apply_env(&mut self) -> Result<(), String> {
+        if let Some(bind) = env_var("BIND") { self.bind = bind; }
+        if let Some(port) = parse_env("PORT")? { self.port = port; }
+        if let Some(folder) = env_var("FOLDER") { self.folder = PathBuf::from(folder); }
+        if let Some(index) = env_var("STATIC_INDEX") { self.static_files.index = PathBuf::from(index); }
+        if let Some(assets) = env_var("STATIC_ASSETS") { self.static_files.assets = PathBuf::from(assets); }
+        if let Some(origins) = env_var("CORS_ORIGINS") {
+            self.cors.origins = origins.split(',').map(|x| String::from(x.trim())).filter(|x| !x.is_empty()).collect();
+        }
+        if let Some(certificate) = env_var("TLS_CERTIFICATE") {
+            self.tls.get_or_insert_with(TlsConfig::default).certificate = PathBuf::from(certificate);
+        }
+        if let Some(key) = env_var("TLS_KEY") {
+            self.tls.get_or_insert_with(TlsConfig::default).key = PathBuf::from(key);
+        }
+        if let Some(keys) = env_var("AUTH_KEYS") {
+            self.auth.get_or_insert_with(AuthConfig::default).keys = PathBuf::from(keys);
+        }
+        if let Some(protect) = env_var("AUTH_PROTECT") {
+            self.auth.get_or_insert_with(AuthConfig::default).protect = protect.split(',')
+                .filter(|x| !x.trim().is_empty())
+                .map(|x| x.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("BIBLE_CLI_AUTH_PROTECT: {}", e))?;
+        }
+        if let Some(log) = env_var("LOG") { self.log = Some(PathBuf::from(log)); }
+        if let Some(limit) = parse_env("JSON_LIMIT")? { self.json_limit = limit; }
+        if let Some(count) = parse_env("MAX_PASSAGE_VERSES")? { self.max_passage_verses = count; }
+        if let Some(size) = parse_env("MAX_PAGE_SIZE")? { self.max_page_size = size; }
+        if let Some(cost) = parse_env("MAX_SEARCH_COST")? { self.max_search_cost = cost; }
+        if let Some(rate) = parse_env("RATE_LIMIT_RATE")? { self.rate_limit.get_or_insert_with(RateLimitConfig::default).rate = rate; }
+        if let Some(burst) = parse_env("RATE_LIMIT_BURST")? { self.rate_limit.get_or_insert_with(RateLimitConfig::default).burst = burst; }
+        if let Some(depth) = parse_env("GRAPHQL_MAX_DEPTH")? { self.graphql.max_depth = depth; }
+        if let Some(complexity) = parse_env("GRAPHQL_MAX_COMPLEXITY")? { self.graphql.max_complexity = complexity; }
+        if let Some(seconds) = parse_env("WATCH")? { self.watch = Some(seconds); }
+        if let Some(compress) = parse_env("COMPRESS")? { self.cache.compress = compress; }
+        // Cache-Control values contain commas, so the settings are separated by semicolons
+        for setting in env_var("CACHE_CONTROL").iter().flat_map(|x| x.split(';')).filter(|x| !x.trim().is_empty()) {
+            let (class, value) = setting.split_once('=')
+                .ok_or_else(|| format!("BIBLE_CLI_CACHE_CONTROL: invalid setting '{}', expected class=value", setting))?;
+            self.cache.cache_control.insert(String::from(class.trim()), String::from(value.trim()));
+        }
+        Ok(())
+    }
+
+    /// Whether any origin may call the api.
+    pub fn cors_permissive(&self) -> bool {
+        self.cors.origins.iter().any(|x| x == "*")
+    }
+
+    pub fn limits(&self) -> ApiLimits {
+        ApiLimits {
+            max_passage_verses: self.max_passage_verses,
+            max_page_size: self.max_page_size,
+            max_search_cost: self.max_search_cost,
+        }
+    }
+
+    pub fn api_keys(&self) -> Result<Option<ApiKeys>, String> {
+        self.auth.as_ref().map(|auth| ApiKeys::from_file(&auth.keys, &auth.protect)).transpose()
+    }
+
+    pub fn cache_policy(&self) -> Result<CachePolicy, String> {
+        let mut policy = CachePolicy::default();
+        for (class, value) in &self.cache.cache_control {
+            policy.set(&format!("{}={}", class, value))?;
+        }
+        Ok(policy)
+    }
+
+    /// Checks all settings, returning one message for each invalid setting.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if self.port == 0 {
+            errors.push(String::from("port: must be between 1 and 65535"));
+        }
+        if (self.bind.as_str(), self.port).to_socket_addrs().map(|mut x| x.next().is_none()).unwrap_or(true) {
+            errors.push(format!("bind: cannot resolve address '{}'", self.bind));
+        }
+        if !self.folder.is_dir() {
+            errors.push(format!("folder: {} is not a directory", self.folder.display()));
+        }
+        if let Some(log) = self.log.as_ref().filter(|x| !x.is_file()) {
+            errors.push(format!("log: {} does not exist", log.display()));
+        }
+        if self.json_limit == 0 {
+            errors.push(String::from("json_limit: must be greater than 0"));
+        }
+        if self.max_passage_verses == 0 {
+            errors.push(String::from("max_passage_verses: must be greater than 0"));
+        }
+        if self.max_page_size == 0 {
+            errors.push(String::from("max_page_size: must be greater than 0"));
+        }
+        if self.max_search_cost == 0 {
+            errors.push(String::from("max_search_cost: must be greater than 0"));
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            if !rate_limit.rate.is_finite() || rate_limit.rate <= 0.0 {
+                errors.push(String::from("rate_limit.rate: must be greater than 0"));
+            }
+            if !rate_limit.burst.is_finite() || rate_limit.burst < 1.0 {
+                errors.push(String::from("rate_limit.burst: must be at least 1"));
+            }
+        }
+        if self.graphql.max_depth == 0 || self.graphql.max_complexity == 0 {
+            errors.push(String::from("graphql: max_depth and max_complexity must be greater than 0"));
+        }
+        if self.watch == Some(0) {
+            errors.push(String::from("watch: must be at least 1 second"));
+        }
+        if self.cors.origins.is_empty() {
+            errors.push(String::from("cors.origins: no origin allowed, use \"*\" to allow any origin"));
+        }
+        for origin in self.cors.origins.iter().filter(|x| *x != "*") {
+            let valid = (origin.starts_with("http://") || origin.starts_with("https://"))
+                && !origin.trim_start_matches("http://").trim_start_matches("https://").contains('/')
+                && HeaderValue::from_str(origin).is_ok();
+            if !valid {
+                errors.push(format!("cors.origins: invalid origin '{}', expected e.g. https://example.com", origin));
+            }
+        }
+        if let Some(Err(e)) = self.tls.as_ref().map(|x| x.server_config()) {
+            errors.push(format!("tls: {}", e));
+        }
+        if let Err(e) = self.api_keys() {
+            errors.push(format!("auth.keys: {}", e));
+        }
+        if let Err(e) = self.cache_policy() {
+            errors.push(format!("cache.cache_control: {}", e));
+        }
+        for (identifier, translation) in &self.translations {
+            if let Some(versification) = &translation.versification {
+                if let Err(e) = versification.parse::<Versification>() {
+                    errors.push(format!("translations.{}.versification: {}", identifier, e));
+                }
+            }
+            if translation.name.as_ref().is_some_and(|x| x.trim().is_empty()) {
+                errors.push(format!("translations.{}.name: must not be empty", identifier));
+            }
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
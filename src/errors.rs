@@ -0,0 +1,136 @@
+use actix_web::{error, HttpRequest, HttpResponse, ResponseError};
+use actix_web::http::{header, StatusCode};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// Error answered by the REST api as `{code, message, details}` json body.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiError {
+    #[serde(skip)]
+    #[schema(ignore)]
+    pub status: StatusCode,
+    #[schema(example = "translation_not_found")]
+    pub code: &'static str,
+    pub message: String,
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<Value>,
+    /// Seconds until a rate limited client may retry, sent as `Retry-After` header
+    #[serde(skip)]
+    #[schema(ignore)]
+    pub retry_after: Option<u64>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: String) -> Self {
+        Self { status, code, message, details: None, retry_after: None }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn not_found(code: &'static str, message: String) -> Self {
+        Self::new(StatusCode::NOT_FOUND, code, message)
+    }
+
+    pub fn bad_request(code: &'static str, message: String) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, message)
+    }
+
+    pub fn unauthorized(message: String) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, "unauthorized", message)
+    }
+
+    pub fn forbidden(message: String) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "forbidden", message)
+    }
+
+    pub fn too_many_requests(retry_after: Duration) -> Self {
+        // round up, so clients retrying after the header never hit an empty bucket again
+        let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        let mut error = Self::new(StatusCode::TOO_MANY_REQUESTS, "rate_limited", String::from("Too many requests, please retry later."))
+            .with_details(serde_json::json!({ "retry_after": seconds }));
+        error.retry_after = Some(seconds);
+        error
+    }
+
+    pub fn query_too_expensive(cost: usize, max_cost: usize) -> Self {
+        Self::bad_request("query_too_expensive", String::from("The query is too expensive, use longer words or a smaller scope."))
+            .with_details(serde_json::json!({ "cost": cost, "max_cost": max_cost }))
+    }
+
+    pub fn internal(message: String) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", message)
+    }
+
+    pub fn translation_not_found(identifier: &str) -> Self {
+        Self::not_found("translation_not_found", String::from("Could not find bible translation with given identifier."))
+            .with_details(serde_json::json!({ "identifier": identifier }))
+    }
+
+    pub fn book_not_found(book: usize) -> Self {
+        Self::not_found("book_not_found", String::from("Could not find book in the bible translation."))
+            .with_details(serde_json::json!({ "book": book }))
+    }
+
+    pub fn chapter_not_found(book: usize, chapter: usize) -> Self {
+        Self::not_found("chapter_not_found", String::from("Could not find chapter in the bible translation."))
+            .with_details(serde_json::json!({ "book": book, "chapter": chapter }))
+    }
+
+    pub fn verse_not_found(book: usize, chapter: usize, verse: usize) -> Self {
+        Self::not_found("verse_not_found", String::from("Could not find verse in the bible translation."))
+            .with_details(serde_json::json!({ "book": book, "chapter": chapter, "verse": verse }))
+    }
+
+    pub fn strong_not_found(strong: &str) -> Self {
+        Self::not_found("strong_not_found", String::from("Could not find strong numbers."))
+            .with_details(serde_json::json!({ "strong": strong }))
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut res = HttpResponse::build(self.status);
+        if let Some(seconds) = self.retry_after {
+            res.insert_header((header::RETRY_AFTER, seconds.to_string()));
+        }
+        if self.status == StatusCode::UNAUTHORIZED {
+            res.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+        }
+        res.json(self)
+    }
+}
+
+// Error handlers for the extractors, so malformed requests get the same json body
+pub fn path_error_handler(err: error::PathError, _req: &HttpRequest) -> error::Error {
+    ApiError::not_found("not_found", String::from("Could not find the requested resource."))
+        .with_details(Value::String(err.to_string()))
+        .into()
+}
+
+pub fn query_error_handler(err: error::QueryPayloadError, _req: &HttpRequest) -> error::Error {
+    ApiError::bad_request("invalid_query", String::from("The query parameters are invalid."))
+        .with_details(Value::String(err.to_string()))
+        .into()
+}
+
+pub fn json_error_handler(err: error::JsonPayloadError, _req: &HttpRequest) -> error::Error {
+    ApiError::bad_request("invalid_body", String::from("The json body is invalid."))
+        .with_details(Value::String(err.to_string()))
+        .into()
+}
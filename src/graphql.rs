@@ -0,0 +1,383 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Enum, Error, Object, Result, Schema, SimpleObject};
+use bible::{Bible, BibleSearcher, BookRange, SearchMode, SearchQuery, StrongKey, BOOKS, format_reference};
+use std::sync::Arc;
+use crate::config::GraphqlConfig;
+use crate::ratelimit::ClientBucket;
+use crate::registry::BibleRegistry;
+use crate::routes::ApiLimits;
+
+pub type BibleSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+/// Complexity of a search for each scan of the bible it is estimated to take.
+const SEARCH_COMPLEXITY: usize = 1000;
+/// List lengths beyond this count as this in the complexity, larger pages are truncated anyway.
+const MAX_LIST_LENGTH: usize = 10000;
+
+/// Builds the schema over the bibles of the registry, limited in query depth and complexity.
+/// Every field counts one, list fields count their estimated length times their children
+/// and searches `SEARCH_COMPLEXITY` times their estimated cost in addition.
+pub fn schema(registry: web::Data<BibleRegistry>, limits: ApiLimits, config: &GraphqlConfig) -> BibleSchema {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .data(registry)
+        .data(limits)
+        .limit_depth(config.max_depth)
+        .limit_complexity(config.max_complexity)
+        .finish()
+}
+
+pub async fn graphql(req: HttpRequest, schema: web::Data<BibleSchema>, request: web::Json<async_graphql::Request>) -> HttpResponse {
+    let request = request.into_inner().data(ClientBucket::of(&req));
+    HttpResponse::Ok().json(schema.execute(request).await)
+}
+
+/// Complexity of a list field with the requested length.
+fn list_complexity(limit: Option<usize>, child_complexity: usize) -> usize {
+    limit.unwrap_or(20).min(MAX_LIST_LENGTH).saturating_mul(child_complexity)
+}
+
+/// Complexity of a search field: its scans of the bible and the verses it returns.
+fn search_complexity(q: &str, scope: Option<&str>, limit: Option<usize>, child_complexity: usize) -> usize {
+    let query = SearchQuery { scope: scope.and_then(|x| x.parse().ok()), ..SearchQuery::new(q) };
+    // malformed queries are rejected by the search itself
+    let cost = query.estimated_cost().unwrap_or(1);
+    cost.saturating_mul(SEARCH_COMPLEXITY).saturating_add(list_complexity(limit, child_complexity))
+}
+
+pub struct Query;
+
+pub struct Translation(Arc<Bible>);
+
+pub struct Book {
+    bible: Arc<Bible>,
+    book: usize,
+}
+
+pub struct Chapter {
+    bible: Arc<Bible>,
+    book: usize,
+    chapter: usize,
+}
+
+pub struct Verse {
+    bible: Arc<Bible>,
+    book: usize,
+    chapter: usize,
+    verse: usize,
+}
+
+pub struct Chunk {
+    bible: Arc<Bible>,
+    book: usize,
+    chunk: bible::Chunk,
+}
+
+pub struct StrongNumber {
+    bible: Arc<Bible>,
+    key: StrongKey,
+    grammar: Option<String>,
+}
+
+pub struct StrongDictEntry {
+    bible: Arc<Bible>,
+    key: StrongKey,
+}
+
+#[derive(SimpleObject)]
+pub struct StrongVariant {
+    variant: String,
+    count: u32,
+}
+
+#[derive(SimpleObject)]
+pub struct SearchResult {
+    total: usize,
+    offset: usize,
+    verses: Vec<Verse>,
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+#[graphql(name = "SearchMode")]
+pub enum GraphqlSearchMode {
+    /// Verses containing all words, words in quotes are searched as phrase
+    All,
+    /// Verses containing at least one of the words
+    Any,
+    /// Verses containing the whole search text as it is
+    Phrase,
+}
+
+impl From<GraphqlSearchMode> for SearchMode {
+    fn from(mode: GraphqlSearchMode) -> Self {
+        match mode {
+            GraphqlSearchMode::All => SearchMode::All,
+            GraphqlSearchMode::Any => SearchMode::Any,
+            GraphqlSearchMode::Phrase => SearchMode::Phrase,
+        }
+    }
+}
+
+impl Verse {
+    fn get(&self) -> Option<&bible::Verse> {
+        self.bible.get_verse(self.book, self.chapter, self.verse)
+    }
+}
+
+#[Object]
+impl Query {
+    /// All loaded bible translations, ordered by identifier
+    #[graphql(complexity = "10 * child_complexity")]
+    async fn translations(&self, ctx: &Context<'_>) -> Vec<Translation> {
+        let registry = ctx.data_unchecked::<web::Data<BibleRegistry>>();
+        registry.translations().iter().filter_map(|x| registry.get(&x.identifier)).map(Translation).collect()
+    }
+
+    /// The translation with the identifier, e.g. `ELB1905STR`
+    async fn translation(&self, ctx: &Context<'_>, identifier: String) -> Option<Translation> {
+        ctx.data_unchecked::<web::Data<BibleRegistry>>().get(&identifier).map(Translation)
+    }
+}
+
+#[Object]
+impl Translation {
+    async fn identifier(&self) -> &str {
+        &self.0.identifier
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// `english`, `hebrew` or `mixed` numbering of the chapters and verses
+    async fn versification(&self) -> String {
+        self.0.versification.to_string()
+    }
+
+    #[graphql(complexity = "66 * child_complexity")]
+    async fn books(&self) -> Vec<Book> {
+        self.0.books.iter().map(|x| Book { bible: self.0.clone(), book: x.nr }).collect()
+    }
+
+    /// The book with the 0-based number
+    async fn book(&self, nr: usize) -> Option<Book> {
+        self.0.get_book(nr).map(|_| Book { bible: self.0.clone(), book: nr })
+    }
+
+    /// The chapter with the 0-based book and chapter number
+    async fn chapter(&self, book: usize, chapter: usize) -> Option<Chapter> {
+        self.0.get_chapter(book, chapter).map(|_| Chapter { bible: self.0.clone(), book, chapter })
+    }
+
+    /// The verse with the 0-based book, chapter and verse number
+    async fn verse(&self, book: usize, chapter: usize, verse: usize) -> Option<Verse> {
+        self.0.get_verse(book, chapter, verse).map(|_| Verse { bible: self.0.clone(), book, chapter, verse })
+    }
+
+    /// The dictionary entry of a strong number, e.g. `G26` or `H430`
+    async fn strong(&self, key: String) -> Result<Option<StrongDictEntry>> {
+        let key: StrongKey = key.parse().map_err(|e: bible::ParseReferenceError| Error::new(e.to_string()))?;
+        Ok(key.get_dict_entry(&self.0).map(|_| StrongDictEntry { bible: self.0.clone(), key }))
+    }
+
+    /// Verses matching the search text, `scope` is a book range like `NT` or `Matthew-John`
+    #[graphql(complexity = "search_complexity(&q, scope.as_deref(), limit, child_complexity)")]
+    async fn search(&self, ctx: &Context<'_>, q: String, mode: Option<GraphqlSearchMode>, scope: Option<String>,
+                    offset: Option<usize>, limit: Option<usize>) -> Result<SearchResult> {
+        let limits = ctx.data_unchecked::<ApiLimits>();
+        let scope = scope.map(|x| x.parse::<BookRange>()).transpose().map_err(|e| Error::new(e.to_string()))?;
+        let query = SearchQuery {
+            q, scope, offset,
+            mode: mode.map(SearchMode::from),
+            limit: Some(limit.unwrap_or(20).min(limits.max_page_size)),
+        };
+        let cost = query.estimated_cost().map_err(|e| Error::new(e.to_string()))?;
+        if cost > limits.max_search_cost {
+            return Err(Error::new(format!("The query is too expensive, its cost {} exceeds {}.", cost, limits.max_search_cost)));
+        }
+        // like the REST search, each search takes its cost from the rate limit of the client
+        if let Some(bucket) = ctx.data_opt::<ClientBucket>() {
+            bucket.charge(cost).map_err(|e| Error::new(e.to_string()))?;
+        }
+        let result = self.0.search_with(&query).map_err(|e| Error::new(e.to_string()))?;
+        Ok(SearchResult {
+            total: result.total,
+            offset: result.offset,
+            verses: result.verses.iter()
+                .map(|x| Verse { bible: self.0.clone(), book: x.book, chapter: x.chapter, verse: x.verse })
+                .collect(),
+        })
+    }
+}
+
+#[Object]
+impl Book {
+    /// 0-based number of the book
+    async fn nr(&self) -> usize {
+        self.book
+    }
+
+    async fn name(&self) -> &str {
+        BOOKS.get(self.book).copied().unwrap_or_default()
+    }
+
+    #[graphql(complexity = "50 * child_complexity")]
+    async fn chapters(&self) -> Vec<Chapter> {
+        let count = self.bible.get_book(self.book).map(|x| x.chapters.len()).unwrap_or(0);
+        (0..count).map(|chapter| Chapter { bible: self.bible.clone(), book: self.book, chapter }).collect()
+    }
+
+    /// The chapter with the 0-based number
+    async fn chapter(&self, nr: usize) -> Option<Chapter> {
+        self.bible.get_chapter(self.book, nr).map(|_| Chapter { bible: self.bible.clone(), book: self.book, chapter: nr })
+    }
+}
+
+#[Object]
+impl Chapter {
+    /// 0-based number of the book
+    async fn book(&self) -> usize {
+        self.book
+    }
+
+    /// 0-based number of the chapter
+    async fn nr(&self) -> usize {
+        self.chapter
+    }
+
+    #[graphql(complexity = "30 * child_complexity")]
+    async fn verses(&self) -> Vec<Verse> {
+        let count = self.bible.get_chapter(self.book, self.chapter).map(|x| x.verses.len()).unwrap_or(0);
+        (0..count).map(|verse| Verse { bible: self.bible.clone(), book: self.book, chapter: self.chapter, verse }).collect()
+    }
+
+    /// The verse with the 0-based number
+    async fn verse(&self, nr: usize) -> Option<Verse> {
+        self.bible.get_verse(self.book, self.chapter, nr)
+            .map(|_| Verse { bible: self.bible.clone(), book: self.book, chapter: self.chapter, verse: nr })
+    }
+}
+
+#[Object]
+impl Verse {
+    /// 0-based number of the book
+    async fn book(&self) -> usize {
+        self.book
+    }
+
+    /// 0-based number of the chapter
+    async fn chapter(&self) -> usize {
+        self.chapter
+    }
+
+    /// 0-based number of the verse
+    async fn nr(&self) -> usize {
+        self.verse
+    }
+
+    /// The reference for humans, e.g. `John 3,16`
+    async fn reference(&self) -> String {
+        format_reference(self.book, self.chapter, self.verse)
+    }
+
+    async fn text(&self) -> String {
+        self.get().map(|x| x.to_string()).unwrap_or_default()
+    }
+
+    /// The parts of the text, tagged with their strong numbers
+    #[graphql(complexity = "20 * child_complexity")]
+    async fn chunks(&self) -> Vec<Chunk> {
+        self.get().iter()
+            .flat_map(|x| x.chunks.iter())
+            .map(|chunk| Chunk { bible: self.bible.clone(), book: self.book, chunk: chunk.clone() })
+            .collect()
+    }
+}
+
+#[Object]
+impl Chunk {
+    async fn text(&self) -> &str {
+        &self.chunk.text
+    }
+
+    async fn strong(&self) -> Option<StrongNumber> {
+        self.chunk.strong.as_ref().map(|strong| StrongNumber {
+            bible: self.bible.clone(),
+            key: StrongKey::for_book(self.book, strong.number),
+            grammar: strong.grammar.clone(),
+        })
+    }
+}
+
+#[Object]
+impl StrongNumber {
+    /// The strong number with its language, e.g. `G26` or `H430`
+    async fn key(&self) -> String {
+        self.key.to_string()
+    }
+
+    async fn number(&self) -> usize {
+        self.key.number
+    }
+
+    async fn greek(&self) -> bool {
+        self.key.greek
+    }
+
+    async fn grammar(&self) -> Option<&str> {
+        self.grammar.as_deref()
+    }
+
+    /// The dictionary entry of the strong number in the same translation
+    async fn entry(&self) -> Option<StrongDictEntry> {
+        self.key.get_dict_entry(&self.bible).map(|_| StrongDictEntry { bible: self.bible.clone(), key: self.key })
+    }
+}
+
+impl StrongDictEntry {
+    fn get(&self) -> Option<&bible::StrongDictEntry> {
+        self.key.get_dict_entry(&self.bible)
+    }
+
+    /// The distinct verses using the strong number, in canonical order.
+    fn verses(&self) -> Vec<(usize, usize, usize)> {
+        let mut verses: Vec<(usize, usize, usize)> = self.get().iter()
+            .flat_map(|x| x.refs.iter())
+            .map(|x| (x.book, x.chapter, x.verse))
+            .collect();
+        verses.sort_unstable();
+        verses.dedup();
+        verses
+    }
+}
+
+#[Object]
+impl StrongDictEntry {
+    async fn key(&self) -> String {
+        self.key.to_string()
+    }
+
+    /// The renderings of the strong number and how often they occur
+    #[graphql(complexity = "10 * child_complexity")]
+    async fn variants(&self) -> Vec<StrongVariant> {
+        self.get().iter()
+            .flat_map(|x| x.variants.iter())
+            .map(|x| StrongVariant { variant: x.variant.clone(), count: x.count })
+            .collect()
+    }
+
+    /// Number of verses using the strong number, the length of `refs` without paging
+    async fn total(&self) -> usize {
+        self.verses().len()
+    }
+
+    /// The verses using the strong number, in canonical order
+    #[graphql(complexity = "list_complexity(limit, child_complexity)")]
+    async fn refs(&self, ctx: &Context<'_>, offset: Option<usize>, limit: Option<usize>) -> Vec<Verse> {
+        let limit = limit.unwrap_or(20).min(ctx.data_unchecked::<ApiLimits>().max_page_size);
+        self.verses().into_iter()
+            .skip(offset.unwrap_or(0))
+            .take(limit)
+            .map(|(book, chapter, verse)| Verse { bible: self.bible.clone(), book, chapter, verse })
+            .collect()
+    }
+}
@@ -5,4 +5,22 @@ pub mod bible;
 pub use bible::*;
 pub use bible::constants::*;
 pub use bible::traits::*;
-pub use bible::zefania_impl::*;
\ No newline at end of file
+pub use bible::zefania_impl::*;
+pub use bible::reference::*;
+pub use bible::cooccurrence::*;
+pub use bible::tokens::*;
+pub use bible::stats::*;
+pub use bible::concordance::*;
+pub use bible::passage::*;
+pub use bible::versification::*;
+pub use bible::compare::*;
+pub use bible::diff::*;
+pub use bible::render::*;
+pub use bible::validate::*;
+pub use bible::convert::*;
+pub use bible::osis_impl::*;
+pub use bible::usfm_impl::*;
+pub use bible::json_impl::*;
+#[cfg(feature = "sqlite")]
+pub use bible::sqlite_impl::*;
+pub use bible::text_impl::*;
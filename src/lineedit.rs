@@ -0,0 +1,246 @@
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::PathBuf;
+
+/// Number of lines kept in the history file.
+const MAX_HISTORY: usize = 1000;
+
+/// Returns the completions of the text before the cursor and the character index
+/// of the text they replace.
+type Completer = Box<dyn Fn(&str) -> (usize, Vec<String>)>;
+
+/// Reads lines from the terminal with cursor movement, a history on the arrow keys
+/// and tab completion. Falls back to plain line reading if stdin is no terminal.
+pub struct LineEditor {
+    history: Vec<String>,
+    history_file: Option<PathBuf>,
+    completer: Completer,
+}
+
+/// Puts the terminal into raw mode while it lives.
+struct RawMode {
+    original: libc::termios,
+}
+
+impl RawMode {
+    fn enable() -> Option<Self> {
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) != 1 {
+                return None;
+            }
+            let mut original: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return None;
+            }
+            let mut raw = original;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+            raw.c_iflag &= !(libc::ICRNL | libc::IXON);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return None;
+            }
+            Some(Self { original })
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+fn read_byte(stdin: &mut io::Stdin) -> io::Result<Option<u8>> {
+    let mut buf = [0u8; 1];
+    match stdin.read(&mut buf)? {
+        0 => Ok(None),
+        _ => Ok(Some(buf[0])),
+    }
+}
+
+/// Reads the rest of an utf-8 character starting with `first`.
+fn read_char(stdin: &mut io::Stdin, first: u8) -> io::Result<Option<char>> {
+    let len = match first {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+    let mut bytes = vec![first];
+    for _ in 1..len {
+        match read_byte(stdin)? {
+            Some(b) => bytes.push(b),
+            None => break,
+        }
+    }
+    Ok(std::str::from_utf8(&bytes).ok().and_then(|x| x.chars().next()))
+}
+
+/// The longest common prefix of the candidates, compared case-insensitively.
+fn common_prefix(candidates: &[String]) -> String {
+    let mut prefix: Vec<char> = candidates[0].chars().collect();
+    for candidate in &candidates[1..] {
+        let len = prefix.iter().zip(candidate.chars())
+            .take_while(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+            .count();
+        prefix.truncate(len);
+    }
+    prefix.into_iter().collect()
+}
+
+impl LineEditor {
+    /// Creates an editor completing the text before the cursor with `completer`, which
+    /// returns the candidates and the character index of the text they replace, so a
+    /// candidate can span several words. The history is kept in `history_file`.
+    pub fn new(history_file: Option<PathBuf>, completer: impl Fn(&str) -> (usize, Vec<String>) + 'static) -> Self {
+        let history = history_file.as_ref()
+            .and_then(|x| fs::read_to_string(x).ok())
+            .map(|x| x.lines().map(String::from).collect())
+            .unwrap_or_default();
+        Self { history, history_file, completer: Box::new(completer) }
+    }
+
+    fn add_history(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() || self.history.last().map(|x| x.as_str()) == Some(line) {
+            return;
+        }
+        self.history.push(String::from(line));
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+        if let Some(path) = &self.history_file {
+            let _ = fs::write(path, self.history.join("\n") + "\n");
+        }
+    }
+
+    /// Reads a line, `None` at the end of the input or on Ctrl-D at an empty line.
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        let raw = match RawMode::enable() {
+            Some(raw) => raw,
+            None => {
+                print!("{}", prompt);
+                io::stdout().flush()?;
+                let mut line = String::new();
+                if io::stdin().lock().read_line(&mut line)? == 0 {
+                    return Ok(None);
+                }
+                let line = String::from(line.trim_end_matches(&['\r', '\n'][..]));
+                self.add_history(&line);
+                return Ok(Some(line));
+            }
+        };
+        let line = self.edit(prompt);
+        drop(raw);
+        println!();
+        let line = line?;
+        if let Some(line) = &line {
+            self.add_history(line);
+        }
+        Ok(line)
+    }
+
+    fn edit(&self, prompt: &str) -> io::Result<Option<String>> {
+        let mut stdin = io::stdin();
+        let mut line: Vec<char> = vec![];
+        let mut cursor = 0;
+        // position in the history, `history.len()` is the line being edited
+        let mut entry = self.history.len();
+        let mut edited: Vec<char> = vec![];
+        self.redraw(prompt, &line, cursor)?;
+        loop {
+            let byte = match read_byte(&mut stdin)? {
+                Some(byte) => byte,
+                None => return Ok(None),
+            };
+            match byte {
+                b'\r' | b'\n' => return Ok(Some(line.into_iter().collect())),
+                // Ctrl-C drops the line
+                3 => {
+                    print!("^C");
+                    return Ok(Some(String::new()));
+                },
+                4 if line.is_empty() => return Ok(None),
+                4 if cursor < line.len() => { line.remove(cursor); },
+                1 => cursor = 0,
+                5 => cursor = line.len(),
+                21 => {
+                    line.drain(..cursor);
+                    cursor = 0;
+                },
+                b'\t' => {
+                    let before: String = line[..cursor].iter().collect();
+                    let (start, candidates) = (self.completer)(&before);
+                    if candidates.is_empty() {
+                        continue;
+                    }
+                    let completion: Vec<char> = if candidates.len() == 1 {
+                        format!("{} ", candidates[0]).chars().collect()
+                    } else {
+                        common_prefix(&candidates).chars().collect()
+                    };
+                    if candidates.len() > 1 && completion.len() <= cursor - start {
+                        print!("\r\n{}\r\n", candidates.join("  "));
+                    }
+                    if completion.len() >= cursor - start {
+                        line.splice(start..cursor, completion.iter().copied());
+                        cursor = start + completion.len();
+                    }
+                },
+                8 | 127 if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                },
+                0x1b => {
+                    let seq = (read_byte(&mut stdin)?, read_byte(&mut stdin)?);
+                    match seq {
+                        (Some(b'['), Some(b'A')) if entry > 0 => {
+                            if entry == self.history.len() {
+                                edited = line.clone();
+                            }
+                            entry -= 1;
+                            line = self.history[entry].chars().collect();
+                            cursor = line.len();
+                        },
+                        (Some(b'['), Some(b'B')) if entry < self.history.len() => {
+                            entry += 1;
+                            line = if entry == self.history.len() { edited.clone() } else { self.history[entry].chars().collect() };
+                            cursor = line.len();
+                        },
+                        (Some(b'['), Some(b'C')) if cursor < line.len() => cursor += 1,
+                        (Some(b'['), Some(b'D')) if cursor > 0 => cursor -= 1,
+                        (Some(b'['), Some(b'H')) => cursor = 0,
+                        (Some(b'['), Some(b'F')) => cursor = line.len(),
+                        (Some(b'['), Some(b'3')) => {
+                            let _ = read_byte(&mut stdin)?;
+                            if cursor < line.len() {
+                                line.remove(cursor);
+                            }
+                        },
+                        _ => {},
+                    }
+                },
+                byte if byte >= 0x20 => {
+                    if let Some(c) = read_char(&mut stdin, byte)? {
+                        line.insert(cursor, c);
+                        cursor += 1;
+                    }
+                },
+                _ => {},
+            }
+            self.redraw(prompt, &line, cursor)?;
+        }
+    }
+
+    fn redraw(&self, prompt: &str, line: &[char], cursor: usize) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        write!(stdout, "\r{}{}\x1b[K", prompt, line.iter().collect::<String>())?;
+        if cursor < line.len() {
+            write!(stdout, "\x1b[{}D", line.len() - cursor)?;
+        }
+        stdout.flush()
+    }
+}
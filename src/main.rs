@@ -2,35 +2,233 @@
 extern crate num;
 
 mod routes;
+mod output;
+mod errors;
+mod openapi;
+mod reload;
+mod registry;
+mod cache;
+mod config;
+mod auth;
+mod ratelimit;
+mod metrics;
+mod graphql;
+mod streaming;
+mod negotiate;
+mod lineedit;
+mod shell;
+mod reader;
 
 use clap::ArgMatches;
 use log4rs::{self, config::RawConfig};
-use log::info;
+use log::{info, warn};
 use actix_web::{App as ActixApp, web, middleware, HttpServer, http};
 use actix_cors::Cors;
 use actix_files;
-use routes::{info, chapter, search, translations, verse, greek_strongs, single_page_app, hebrew_strongs};
+use routes::{single_page_app, api_v1, api_legacy, api_admin, api_graphql, healthz, readyz};
+use output::{OutputFormat, DiffFormat, VerseFormat, VerseRecord, csv_row, print_verses, BOLD, CYAN, GREEN, RED, RESET};
+use errors::{path_error_handler, query_error_handler, json_error_handler};
+use openapi::{openapi_json, api_docs};
+use reload::BibleFolder;
+use registry::BibleRegistry;
+use config::ServerConfig;
+use auth::Scope;
+use ratelimit::RateLimiter;
+use metrics::{Metrics, RequestMetrics};
+use shell::Shell;
+use reader::{ReadOptions, format_chapters, page, terminal_width, is_terminal};
 
-use bible::{Bible, ZefaniaBible, BibleSearcher, BibleParser, BOOKS, Translation};
+use bible::{Bible, ZefaniaBible, BibleSearcher, BibleParser, BOOKS, Translation, StrongKey, StrongRefQuery, RelatedQuery, related, BookRange, StatsQuery, Vocabulary, StatsUnit, Keyword, ConcordanceLine, ConcordanceOptions, concordance, full_concordance, DEFAULT_CONTEXT, Passage, VerseRef, Chunk, Comparison, ChapterText, Layout, RenderOptions, parse_passages, get_passages, compare, format_reference, BibleDiff, DiffOp, DiffOptions, VerseChange, diff_bibles, Severity, Validation, Versification, validate, FORMATS, convert, detect_format, find_format};
 
 use std::fs;
-use std::time::Instant;
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use std::sync::Arc;
 use clap::{arg, command, Command};
 use serde_json;
 use glob::glob;
 
 
 
-#[actix_rt::main]
-async fn main() -> std::io::Result<()> {
-    // Configure logging
-    let config = String::from_utf8(include_bytes!("log4rs.yaml").to_vec()).unwrap();
-    let log4rs_config: RawConfig = serde_yaml::from_str(config.as_str()).unwrap();
-	log4rs::init_raw_config(log4rs_config).unwrap();
+fn print_unified_diff(diff: &BibleDiff, old_path: &str, new_path: &str) {
+    println!("--- {} ({})", old_path, diff.old.identifier);
+    println!("+++ {} ({})", new_path, diff.new.identifier);
+    for verse in &diff.verses {
+        println!("@@ {} @@", verse.reference);
+        if verse.old_text == verse.new_text {
+            println!(" {}", verse.new_text.as_deref().unwrap_or(""));
+        } else {
+            if let Some(text) = &verse.old_text {
+                println!("-{}", text);
+            }
+            if let Some(text) = &verse.new_text {
+                println!("+{}", text);
+            }
+        }
+        for tag in &verse.tags {
+            println!("~{}: {} -> {}", tag.word, tag.old.as_deref().unwrap_or("-"), tag.new.as_deref().unwrap_or("-"));
+        }
+    }
+}
 
-    info!("Started bible ...");
+/// Prints the issues as `path:line:column: severity: message [kind]` and a summary of the file.
+fn print_validation(validation: &Validation) {
+    for issue in &validation.issues {
+        let severity = match issue.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match (issue.line, issue.column) {
+            (Some(line), Some(column)) => println!("{}:{}:{}: {}: {} [{}]", validation.path, line, column, severity, issue.message, issue.kind),
+            _ => println!("{}: {}: {} [{}]", validation.path, severity, issue.message, issue.kind),
+        }
+    }
+    let numbering = validation.versification.as_ref().map(|x| format!(", {} numbering", x)).unwrap_or_default();
+    println!("{}: {}, {} books, {} chapters, {} verses{}: {} errors, {} warnings", validation.path,
+        validation.identifier.as_deref().unwrap_or("no identifier"), validation.books, validation.chapters, validation.verses,
+        numbering, validation.errors, validation.warnings);
+}
 
+/// Prints the word diff, with escape codes for the colors if `color` is set.
+fn print_colored_diff(diff: &BibleDiff, color: bool) {
+    let paint = |code: &'static str| if color { code } else { "" };
+    let (bold, red, green, cyan, reset) = (paint(BOLD), paint(RED), paint(GREEN), paint(CYAN), paint(RESET));
+    for verse in &diff.verses {
+        let label = match verse.change {
+            VerseChange::Added => "added",
+            VerseChange::Removed => "removed",
+            VerseChange::Changed => "changed",
+        };
+        println!("{}{}{} ({})", bold, verse.reference, reset, label);
+        let words: Vec<String> = verse.words.iter().map(|x| match x.op {
+            DiffOp::Equal => x.text.clone(),
+            DiffOp::Delete => format!("{}[-{}-]{}", red, x.text, reset),
+            DiffOp::Insert => format!("{}{{+{}+}}{}", green, x.text, reset),
+        }).collect();
+        println!("  {}", words.join(" "));
+        for tag in &verse.tags {
+            println!("  {}{}{}: {} -> {}", cyan, tag.word, reset, tag.old.as_deref().unwrap_or("-"), tag.new.as_deref().unwrap_or("-"));
+        }
+    }
+    println!("{} verses added, {} removed, {} changed, {} unchanged", diff.added, diff.removed, diff.changed, diff.unchanged);
+}
+
+/// Loads the comma separated translations, given as xml files or as identifiers of the bibles in `folder`.
+fn load_translations(list: &str, folder: &str) -> Result<Vec<Bible>, String> {
+    let mut in_folder: Option<Vec<Bible>> = None;
+    let mut bibles = vec![];
+    for name in list.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        if std::path::Path::new(name).is_file() {
+            bibles.push(ZefaniaBible::parse(name).map_err(|e| format!("Could not parse {}: {}", name, e))?);
+            continue;
+        }
+        if in_folder.is_none() {
+            let mut parsed = vec![];
+            for path in fs::read_dir(folder).map_err(|e| format!("Could not read {}: {}", folder, e))?.flatten() {
+                if path.file_name().to_string_lossy().ends_with("xml") {
+                    parsed.push(ZefaniaBible::parse(&path.path().to_string_lossy()).map_err(|e| e.to_string())?);
+                }
+            }
+            in_folder = Some(parsed);
+        }
+        let bible = in_folder.as_ref().unwrap().iter().find(|x| x.identifier == name)
+            .ok_or_else(|| format!("Could not find bible translation {} in {}.", name, folder))?;
+        bibles.push(bible.clone());
+    }
+    Ok(bibles)
+}
+
+fn chunks_text(verse: &VerseRef) -> String {
+    verse.chunks.iter().flatten().map(|x| x.text.as_str()).collect::<Vec<&str>>().join(" ")
+}
+
+fn verse_record(book: usize, chapter: usize, verse: usize, chunks: &[Chunk]) -> VerseRecord {
+    VerseRecord {
+        reference: format_reference(book, chapter, verse),
+        book: book + 1,
+        chapter: chapter + 1,
+        verse: verse + 1,
+        text: chunks.iter().map(|x| x.text.as_str()).collect::<Vec<&str>>().join(" "),
+    }
+}
+
+/// Prints the verses of the translations below each other, with the reference in
+/// the numbering of a translation if it differs.
+fn print_comparison(comparison: &Comparison) {
+    let width = comparison.translations.iter().map(|x| x.identifier.len()).max().unwrap_or(0);
+    for verse in &comparison.verses {
+        println!("{}", verse.reference);
+        for (translation, text) in comparison.translations.iter().zip(&verse.texts) {
+            let text = match text {
+                Some(r) if (r.book, r.chapter, r.verse) != (verse.book, verse.chapter, verse.verse) =>
+                    format!("({}) {}", format_reference(r.book, r.chapter, r.verse), chunks_text(r)),
+                Some(r) => chunks_text(r),
+                None => String::from("-"),
+            };
+            println!("  {:width$}  {}", translation.identifier, text, width = width);
+        }
+    }
+}
+
+fn print_concordance_lines(lines: &[ConcordanceLine]) {
+    let reference_width = lines.iter().map(|x| x.reference().chars().count()).max().unwrap_or(0);
+    let left_width = lines.iter().map(|x| x.left.chars().count()).max().unwrap_or(0);
+    for l in lines {
+        println!("  {:<rw$}  {:>lw$}  {}  {}", l.reference(), l.left, l.keyword, l.right, rw = reference_width, lw = left_width);
+    }
+}
+
+fn concordance_csv_row(l: &ConcordanceLine) -> String {
+    csv_row(&[l.reference(), (l.book + 1).to_string(), (l.chapter + 1).to_string(), (l.verse + 1).to_string(),
+        l.left.clone(), l.keyword.clone(), l.right.clone()])
+}
+
+/// The configuration of `serve` from the config file, the environment and the
+/// command line options. Exits with a list of the invalid settings.
+fn server_config(args: &ArgMatches) -> ServerConfig {
+    let exit_invalid = |errors: Vec<String>| -> ! {
+        eprintln!("Invalid configuration:");
+        for e in errors {
+            eprintln!("  {}", e);
+        }
+        std::process::exit(1);
+    };
+    let path = args.value_of("config").map(String::from).or_else(|| std::env::var("BIBLE_CLI_CONFIG").ok());
+    let mut config = match path {
+        Some(path) => ServerConfig::from_file(Path::new(&path)).unwrap_or_else(|e| exit_invalid(vec![e])),
+        None => ServerConfig::default(),
+    };
+    if let Err(e) = config.apply_env() {
+        exit_invalid(vec![e]);
+    }
+
+    if let Some(bind) = args.value_of("bind") { config.bind = String::from(bind); }
+    if args.is_present("port") { config.port = args.value_of_t("port").unwrap_or_else(|e| e.exit()); }
+    if let Some(folder) = args.value_of("folder") { config.folder = PathBuf::from(folder); }
+    if args.is_present("max-passage-verses") {
+        config.max_passage_verses = args.value_of_t("max-passage-verses").unwrap_or_else(|e| e.exit());
+    }
+    if let Ok(seconds) = args.value_of_t("watch") { config.watch = Some(seconds); }
+    if args.is_present("no-api-docs") { config.api_docs = false; }
+    if args.is_present("admin") { config.admin = true; }
+    if args.is_present("compress") { config.cache.compress = true; }
+    for setting in args.values_of("cache-control").into_iter().flatten() {
+        match setting.split_once('=') {
+            Some((class, value)) => { config.cache.cache_control.insert(String::from(class.trim()), String::from(value.trim())); },
+            None => exit_invalid(vec![format!("--cache-control: invalid setting '{}', expected class=value", setting)]),
+        }
+    }
+
+    if let Err(errors) = config.validate() {
+        exit_invalid(errors);
+    }
+    config
+}
+
+#[actix_rt::main]
+async fn main() -> std::io::Result<()> {
     let matches = command!()
         .arg(arg!(-v --verbose ... "Sets the level of verbosity"))
         .subcommand(
@@ -39,37 +237,375 @@ async fn main() -> std::io::Result<()> {
                 .arg(arg!([BIBLE] "Sets the bible xml file to use").required(true))
                 .arg(arg!(-o --outdir ... "Output directory"))
         )
+        .subcommand(
+            Command::new("read")
+                .about("prints a passage of the bible")
+                .arg(arg!([BIBLE] "Sets the bible xml file to use").required(true))
+                .arg(arg!([REFERENCE] "passages separated by ;, e.g. \"John 3\" or \"Matthew 5:1-7:29; John 3,16\"").required(true))
+                .arg(arg!(--layout [layout] "A line per verse or a paragraph per chapter (default: lines)").possible_values(["lines", "paragraph"]))
+                .arg(arg!(--"no-verse-numbers" "Leave out the verse numbers"))
+                .arg(arg!(--strongs "Show the strong numbers after the tagged words"))
+                .arg(arg!(-w --width [columns] "Wrap the lines at this column, 0 to not wrap (default: terminal width)"))
+                .arg(arg!(--color [when] "Bold headings, colored verse numbers and red letters (default: auto)").possible_values(["auto", "always", "never"]))
+                .arg(arg!(--"no-pager" "Print the text instead of showing it through $PAGER"))
+                .arg(arg!(--format [format] "Output format (default: text)").possible_values(["text", "json", "jsonl", "csv", "tsv"]))
+        )
         .subcommand(
             Command::new("search")
                 .about("searches in the bible")
                 .arg(arg!([BIBLE] "Sets the bible xml file to use").required(true))
-                .arg(arg!([TERM] "search term"))
+                .arg(arg!([TERM] "search term").required(true))
                 .arg(arg!(-t --times [time] "Execute search given times"))
+                .arg(arg!(--format [format] "Output format (default: text)").possible_values(["text", "json", "jsonl", "csv", "tsv"]))
+        )
+        .subcommand(
+            Command::new("strongs")
+                .about("shows the references of a strong number or the strong numbers related to it")
+                .arg(arg!([BIBLE] "Sets the bible xml file to use").required(true))
+                .arg(arg!([NUMBER] "strong number, e.g. G4102 or H430").required(true))
+                .arg(arg!(--related "List the strong numbers occurring most often together with this one"))
+                .arg(arg!(--scope [scope] "Co-occurrence scope (default: verse)").possible_values(["verse", "window"]))
+                .arg(arg!(--window [size] "Window size in tagged words for the window scope (default: 5)"))
+                .arg(arg!(--measure [measure] "Association measure (default: llr)").possible_values(["pmi", "llr"]))
+                .arg(arg!(--"min-count" [count] "Minimum number of co-occurrences (default: 2)"))
+                .arg(arg!(-l --limit [limit] "Maximum number of results"))
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("computes word frequencies, hapax legomena and vocabulary statistics")
+                .arg(arg!([BIBLE] "Sets the bible xml file to use").required(true))
+                .arg(arg!(-u --unit [unit] "Count words or strong numbers (default: word)").possible_values(["word", "strong"]))
+                .arg(arg!(-b --books [books] "Book range, e.g. NT, John or Matthew-John (default: all books)"))
+                .arg(arg!(--top [count] "Number of most frequent items to list (default: 50)"))
+                .arg(arg!(--hapax "List the hapax legomena"))
+                .arg(arg!(-i --item [item] "Word or strong number to show the per book frequencies for"))
+                .arg(arg!(--format [format] "Output format (default: table)").possible_values(["table", "csv", "json"]))
+        )
+        .subcommand(
+            Command::new("concordance")
+                .about("generates a keyword in context concordance")
+                .arg(arg!([BIBLE] "Sets the bible xml file to use").required(true))
+                .arg(arg!([KEYWORD] "word, phrase or strong number, e.g. Liebe, \"im Anfang\" or G26"))
+                .arg(arg!(--all "Generate the concordance of all words of the translation"))
+                .arg(arg!(-c --context [words] "Number of words of context on each side (default: 5)"))
+                .arg(arg!(--sort [sort] "Sort by reference or by the following words (default: reference)").possible_values(["reference", "following"]))
+                .arg(arg!(-b --books [books] "Book range, e.g. NT, John or Matthew-John (default: all books)"))
+                .arg(arg!(--"max-count" [count] "Leave out words occurring more often than this (only with --all)"))
+                .arg(arg!(--format [format] "Output format (default: table)").possible_values(["table", "csv"]))
+        )
+        .subcommand(
+            Command::new("compare")
+                .about("shows a passage of several translations aligned verse by verse")
+                .arg(arg!([REFERENCE] "passage, e.g. \"John 3\" or \"Malachi 4:1-6\"").required(true))
+                .arg(arg!(-t --translations <translations> "Comma separated bible xml files or identifiers of the bibles in the folder"))
+                .arg(arg!(-f --folder [folder] "Path to the bible XML files (default: ./bibles)"))
+                .arg(arg!(--format [format] "Output format (default: table)").possible_values(["table", "json"]))
+        )
+        .subcommand(
+            Command::new("shell")
+                .about("reads, searches and compares bibles interactively, parsing them only once")
+                .arg(arg!([BIBLES] "Bible xml files or identifiers of the bibles in the folder").required(true).multiple_values(true))
+                .arg(arg!(-f --folder [folder] "Path to the bible XML files (default: ./bibles)"))
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("compares two bibles or two revisions of a bible file verse by verse")
+                .arg(arg!([OLD] "Sets the old bible xml file").required(true))
+                .arg(arg!([NEW] "Sets the new bible xml file").required(true))
+                .arg(arg!(-b --books [books] "Book range, e.g. NT, John or Matthew-John (default: all books)"))
+                .arg(arg!(--"ignore-tags" "Compare the wording only, e.g. of two different translations"))
+                .arg(arg!(--format [format] "Output format (default: color)").possible_values(["color", "unified", "json"]))
+        )
+        .subcommand(
+            Command::new("convert")
+                .about("converts a bible between zefania, osis, usfm, json, sqlite and text")
+                .arg(arg!([INPUT] "Bible file, or a directory of usfm files").required(true))
+                .arg(arg!(--to <format> "Format to write").possible_values(FORMATS.iter().filter(|x| x.can_write()).map(|x| x.name)))
+                .arg(arg!(--from [format] "Format of the input (default: detected)").possible_values(FORMATS.iter().filter(|x| x.can_read()).map(|x| x.name)))
+                .arg(arg!(-o --output <path> "File to write, or the directory of the usfm files"))
+        )
+        .subcommand(
+            Command::new("validate")
+                .about("checks bible xml files for errors, gaps and an unusual numbering")
+                .arg(arg!([FILES] "Bible xml files to check").required(true).multiple_values(true))
+                .arg(arg!(--versification [scheme] "Numbering to compare the books to (default: the detected one)").possible_values(["english", "hebrew"]))
+                .arg(arg!(--format [format] "Output format (default: text)").possible_values(["text", "json"]))
         )
         .subcommand(
             Command::new("serve")
                 .about("serves the bible REST api")
+                .arg(arg!(-c --config [file] "Server configuration file (.toml, .yaml or .yml)"))
+                .arg(arg!(--bind [address] "Address to bind to (default: 0.0.0.0)"))
                 .arg(arg!(-p --port [port] "Port to host the API (default: 8000)"))
                 .arg(arg!(-f --folder [folder] "Path to the bible XML files"))
+                .arg(arg!(--"no-api-docs" "Do not serve the api documentation page at /api/docs"))
+                .arg(arg!(--"max-passage-verses" [count] "Maximum number of verses answered by the passage endpoint (default: 2000)"))
+                .arg(arg!(--watch [seconds] "Check the folder for new, changed or deleted bibles every given seconds"))
+                .arg(arg!(--admin "Serve the administration endpoints at /api/v1/admin"))
+                .arg(arg!(--"cache-control" [setting] "Cache-Control header of a route class (meta, text, search, strongs or analysis), e.g. search=no-store").multiple_occurrences(true))
+                .arg(arg!(--compress "Compress the responses with gzip, brotli or zstd as accepted by the client"))
         )
         .get_matches();
 
-    if let Some(matches) = matches.subcommand_matches("search") {
+    let server_config = matches.subcommand_matches("serve").map(server_config);
+
+    // Configure logging
+    match server_config.as_ref().and_then(|x| x.log.as_ref()) {
+        Some(path) => if let Err(e) = log4rs::init_file(path, Default::default()) {
+            eprintln!("Invalid configuration:\n  log: {}: {}", path.display(), e);
+            std::process::exit(1);
+        },
+        None => {
+            let config = String::from_utf8(include_bytes!("log4rs.yaml").to_vec()).unwrap();
+            let log4rs_config: RawConfig = serde_yaml::from_str(config.as_str()).unwrap();
+            log4rs::init_raw_config(log4rs_config).unwrap();
+        },
+    }
+
+    info!("Started bible ...");
+
+    if let Some(matches) = matches.subcommand_matches("read") {
+        let passages = match parse_passages(matches.value_of("REFERENCE").unwrap()) {
+            Ok(passages) if !passages.is_empty() => passages,
+            Ok(_) => {
+                eprintln!("At least one passage is required.");
+                std::process::exit(1);
+            },
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        let bible = ZefaniaBible::parse(matches.value_of("BIBLE").unwrap()).unwrap();
+        let result = get_passages(&bible, &passages);
+        let chapters = ChapterText::from_passages(&result.passages);
+        if chapters.is_empty() {
+            eprintln!("Could not find {} in {}.", matches.value_of("REFERENCE").unwrap(), bible.identifier);
+            std::process::exit(1);
+        }
+        let format: VerseFormat = matches.value_of("format").unwrap_or("text").parse().unwrap();
+        if format != VerseFormat::Text {
+            let verses: Vec<VerseRecord> = chapters.iter()
+                .flat_map(|x| x.verses.iter().map(move |(verse, chunks)| verse_record(x.book, x.chapter, *verse, chunks)))
+                .collect();
+            print_verses(&verses, format)?;
+            return Ok(());
+        }
+        let options = ReadOptions {
+            render: RenderOptions {
+                verse_numbers: !matches.is_present("no-verse-numbers"),
+                strongs: matches.is_present("strongs"),
+                layout: match matches.value_of("layout") {
+                    Some("paragraph") => Layout::Paragraph,
+                    _ => Layout::Lines,
+                },
+            },
+            width: match matches.is_present("width") {
+                true => Some(matches.value_of_t("width").unwrap_or_else(|e| e.exit())).filter(|&x: &usize| x > 0),
+                false => terminal_width(),
+            },
+            color: match matches.value_of("color") {
+                Some("always") => true,
+                Some("never") => false,
+                _ => is_terminal(),
+            },
+        };
+        page(&format_chapters(&chapters, &options), !matches.is_present("no-pager"))?;
+    } else if let Some(matches) = matches.subcommand_matches("search") {
         let bible = matches.value_of("BIBLE").unwrap();
         let bible = ZefaniaBible::parse(bible).unwrap();
         let term = String::from(matches.value_of("TERM").unwrap());
         let count = ArgMatches::value_of_t(matches,"times").unwrap_or(1);
+        let format: VerseFormat = matches.value_of("format").unwrap_or("text").parse().unwrap();
 
-        println!("Search for {} {} times ...", term, count);
+        eprintln!("Search for {} {} times ...", term, count);
 
         let now = Instant::now();
         let mut res = vec![];
         for _ in 0..count {
-            res = bible.search_parallel(&term).unwrap();
+            res = match bible.search_parallel(&term) {
+                Ok(res) => res,
+                Err(e) => {
+                    eprintln!("Could not search for {}: {}", term, e);
+                    std::process::exit(1);
+                }
+            };
         }
-        println!("Found {} occurrences parallel in {}ms (searched {} times)!", res.len(), (now.elapsed().as_millis() as f32 / count as f32), count);
-        for v in res {
-            println!("  {}", v.to_string());
+        eprintln!("Found {} occurrences parallel in {}ms (searched {} times)!", res.len(), (now.elapsed().as_millis() as f32 / count as f32), count);
+        if format == VerseFormat::Text {
+            for v in res {
+                println!("{}", v.to_string().trim_start());
+            }
+        } else {
+            let verses: Vec<VerseRecord> = res.iter()
+                .map(|x| verse_record(x.book, x.chapter, x.verse, x.chunks.as_deref().unwrap_or(&[])))
+                .collect();
+            print_verses(&verses, format)?;
+        }
+    } else if let Some(matches) = matches.subcommand_matches("strongs") {
+        let bible = matches.value_of("BIBLE").unwrap();
+        let bible = ZefaniaBible::parse(bible).unwrap();
+        let key: StrongKey = match matches.value_of("NUMBER").unwrap().parse() {
+            Ok(key) => key,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        let entry = match key.get_dict_entry(&bible) {
+            Some(entry) => entry,
+            None => {
+                eprintln!("Could not find strong number {} in {}.", key, bible.identifier);
+                std::process::exit(1);
+            }
+        };
+
+        if matches.is_present("related") {
+            let query = RelatedQuery {
+                scope: matches.value_of("scope").map(|x| x.parse().unwrap()),
+                window: matches.is_present("window").then(|| matches.value_of_t("window").unwrap_or_else(|e| e.exit())),
+                measure: matches.value_of("measure").map(|x| x.parse().unwrap()),
+                min_count: matches.is_present("min-count").then(|| matches.value_of_t("min-count").unwrap_or_else(|e| e.exit())),
+                limit: matches.is_present("limit").then(|| matches.value_of_t("limit").unwrap_or_else(|e| e.exit())),
+            };
+            let res = related(&bible, key, &query);
+            println!("{} occurs in {} of {} units, related strong numbers:", key, res.frequency, res.units);
+            println!("  {:<8} {:<20} {:>8} {:>8} {:>10}", "strong", "variant", "count", "freq", "score");
+            for n in res.neighbours {
+                println!("  {:<8} {:<20} {:>8} {:>8} {:>10.2}", n.strong.to_string(), n.variant.unwrap_or_default(), n.count, n.frequency, n.score);
+            }
+        } else {
+            let query = StrongRefQuery { limit: matches.is_present("limit").then(|| matches.value_of_t("limit").unwrap_or_else(|e| e.exit())), ..Default::default() };
+            let page = entry.get_page(&bible, &query);
+            println!("{} occurs {} times:", key, page.total);
+            for v in &page.variants {
+                println!("  {} ({})", v.variant, v.count);
+            }
+            for v in page.refs {
+                println!("  {}", v.to_string());
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("stats") {
+        let bible = matches.value_of("BIBLE").unwrap();
+        let bible = ZefaniaBible::parse(bible).unwrap();
+        let books: Option<BookRange> = match matches.value_of("books").map(|x| x.parse()).transpose() {
+            Ok(books) => books,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        let query = StatsQuery {
+            unit: matches.value_of("unit").map(|x| x.parse().unwrap()),
+            books,
+            top: ArgMatches::value_of_t(matches, "top").ok(),
+            hapax: Some(matches.is_present("hapax")),
+            item: matches.value_of("item").map(String::from),
+        };
+        let format: OutputFormat = matches.value_of("format").unwrap_or("table").parse().unwrap();
+        let vocabulary = Vocabulary::build(&bible, query.unit.unwrap_or(StatsUnit::Word), query.books);
+        let stats = vocabulary.stats(&query);
+
+        match format {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+            OutputFormat::Csv => {
+                if query.item.is_some() {
+                    println!("{}", csv_row(&["book", "name", "tokens", "types", "count", "per_thousand"]));
+                    for book in &stats.books {
+                        let item = book.item.as_ref().unwrap();
+                        println!("{}", csv_row(&[(book.book + 1).to_string(), book.name.to_string(), book.tokens.to_string(),
+                            book.types.to_string(), item.count.to_string(), format!("{:.3}", item.per_thousand)]));
+                    }
+                } else if let Some(hapax_legomena) = &stats.hapax_legomena {
+                    println!("item");
+                    for item in hapax_legomena {
+                        println!("{}", csv_row(&[item]));
+                    }
+                } else {
+                    println!("{}", csv_row(&["rank", "item", "count", "per_thousand"]));
+                    for (i, f) in stats.frequencies.iter().enumerate() {
+                        println!("{}", csv_row(&[(i + 1).to_string(), f.item.clone(), f.count.to_string(), format!("{:.3}", f.per_thousand)]));
+                    }
+                }
+            },
+            OutputFormat::Table => {
+                println!("Tokens: {}, types: {}, hapax legomena: {}", stats.tokens, stats.types, stats.hapax_count);
+                if let Some(item) = &stats.item {
+                    println!("\n{} occurs {} times ({:.3} per 1000 tokens)", item.item, item.count, item.per_thousand);
+                }
+                println!("\n  {:>5}  {:<24} {:>8} {:>10}", "rank", "item", "count", "per 1000");
+                for (i, f) in stats.frequencies.iter().enumerate() {
+                    println!("  {:>5}  {:<24} {:>8} {:>10.3}", i + 1, f.item, f.count, f.per_thousand);
+                }
+                println!("\n  {:<20} {:>8} {:>8} {:>8} {:>10}", "book", "tokens", "types", "count", "per 1000");
+                for book in &stats.books {
+                    let (count, per_thousand) = book.item.as_ref().map(|x| (x.count.to_string(), format!("{:.3}", x.per_thousand))).unwrap_or_default();
+                    println!("  {:<20} {:>8} {:>8} {:>8} {:>10}", book.name, book.tokens, book.types, count, per_thousand);
+                }
+                if let Some(hapax_legomena) = &stats.hapax_legomena {
+                    println!("\nHapax legomena:");
+                    for item in hapax_legomena {
+                        println!("  {}", item);
+                    }
+                }
+            },
+        }
+    } else if let Some(matches) = matches.subcommand_matches("concordance") {
+        let keyword: Option<Keyword> = match matches.value_of("KEYWORD").map(|x| x.parse()).transpose() {
+            Ok(keyword) => keyword,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        if keyword.is_none() && !matches.is_present("all") {
+            eprintln!("Either a keyword or --all is required.");
+            std::process::exit(1);
+        }
+        let books: Option<BookRange> = match matches.value_of("books").map(|x| x.parse()).transpose() {
+            Ok(books) => books,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        let bible = matches.value_of("BIBLE").unwrap();
+        let bible = ZefaniaBible::parse(bible).unwrap();
+        let options = ConcordanceOptions {
+            context: ArgMatches::value_of_t(matches, "context").unwrap_or(DEFAULT_CONTEXT),
+            sort: matches.value_of("sort").unwrap_or("reference").parse().unwrap(),
+            books,
+        };
+        let format: OutputFormat = matches.value_of("format").unwrap_or("table").parse().unwrap();
+
+        if let Some(keyword) = keyword {
+            let lines = concordance(&bible, &keyword, &options);
+            if format == OutputFormat::Csv {
+                println!("{}", csv_row(&["reference", "book", "chapter", "verse", "left", "keyword", "right"]));
+                for l in &lines {
+                    println!("{}", concordance_csv_row(l));
+                }
+            } else {
+                println!("{} occurrences of {}:", lines.len(), matches.value_of("KEYWORD").unwrap());
+                print_concordance_lines(&lines);
+            }
+        } else {
+            let entries = full_concordance(&bible, &options, ArgMatches::value_of_t(matches, "max-count").ok());
+            if format == OutputFormat::Csv {
+                println!("{}", csv_row(&["word", "reference", "book", "chapter", "verse", "left", "keyword", "right"]));
+                for entry in &entries {
+                    for l in &entry.lines {
+                        println!("{},{}", csv_row(&[&entry.word]), concordance_csv_row(l));
+                    }
+                }
+            } else {
+                println!("Concordance of {}\n", bible.name);
+                for entry in &entries {
+                    println!("{} ({})", entry.word.to_uppercase(), entry.lines.len());
+                    print_concordance_lines(&entry.lines);
+                    println!();
+                }
+            }
         }
     } else if let Some(matches) = matches.subcommand_matches("export") {
         let bible = matches.value_of("BIBLE").unwrap();
@@ -78,7 +614,7 @@ async fn main() -> std::io::Result<()> {
         for path in glob(bible).expect("") {
             let bible = ZefaniaBible::parse(path.unwrap().to_str().unwrap()).unwrap();
             translations.push(bible.get_translation());
-            println!("Export json files for {} ...", bible.name);
+            info!("Export json files for {} ...", bible.name);
             for book in bible.books {
                 let dir = format!("{}/bibles/{}/{}", &outdir, bible.identifier, book.nr);
                 fs::create_dir_all(&dir)?;
@@ -97,7 +633,8 @@ async fn main() -> std::io::Result<()> {
                     }
                 }
             }
-            println!("  ... done.\nExport json files for the strong numbers ...");
+            info!("  ... done.");
+            info!("Export json files for the strong numbers ...");
             let dir = format!("{}/bibles/{}/greek_strongs", &outdir, bible.identifier);
             fs::create_dir_all(&dir)?;
             for (strong_number, entry) in bible.greek_strong_dict {
@@ -115,61 +652,226 @@ async fn main() -> std::io::Result<()> {
             }
         }
 
-        println!("Export translations.json file ...");
+        info!("Export translations.json file ...");
         let path = format!("{}/bibles/translations.json", outdir);
         let translations_string = serde_json::to_string(&translations)?;
         fs::write(path, translations_string)?;
 
-        println!("  ... done.");
-    } else if let Some(serve_args) = matches.subcommand_matches("serve") {
-        let port = ArgMatches::value_of_t(serve_args,"port").unwrap_or(8000);
-        let folder = String::from(ArgMatches::value_of(serve_args, "folder").unwrap_or("./bibles"));
-        let mut bibles: Vec<Bible> = vec![];
-        for path in fs::read_dir(folder)? {
-            if let Ok(path) = path {
-                if path.file_name().into_string().unwrap().ends_with("xml") {
-                    let bible = ZefaniaBible::parse(path.path().into_os_string().into_string().unwrap().as_str()).unwrap();
-                    bibles.push(bible);
+        info!("  ... done.");
+    } else if let Some(matches) = matches.subcommand_matches("compare") {
+        let passage: Passage = match matches.value_of("REFERENCE").unwrap().parse() {
+            Ok(passage) => passage,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        let folder = matches.value_of("folder").unwrap_or("./bibles");
+        let bibles = match load_translations(matches.value_of("translations").unwrap(), folder) {
+            Ok(bibles) => bibles,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        let comparison = compare(&bibles.iter().collect::<Vec<&Bible>>(), &passage);
+        let format: OutputFormat = matches.value_of("format").unwrap_or("table").parse().unwrap();
+        if format == OutputFormat::Json {
+            println!("{}", serde_json::to_string_pretty(&comparison)?);
+        } else {
+            print_comparison(&comparison);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("shell") {
+        let folder = matches.value_of("folder").unwrap_or("./bibles");
+        let list = matches.values_of("BIBLES").unwrap().collect::<Vec<&str>>().join(",");
+        let bibles = match load_translations(&list, folder) {
+            Ok(bibles) if !bibles.is_empty() => bibles,
+            Ok(_) => {
+                eprintln!("No bible translation given.");
+                std::process::exit(1);
+            },
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        Shell::new(bibles).run();
+    } else if let Some(matches) = matches.subcommand_matches("convert") {
+        let (input, output) = (matches.value_of("INPUT").unwrap(), matches.value_of("output").unwrap());
+        let from = match matches.value_of("from") {
+            Some(name) => find_format(name).unwrap(),
+            None => match detect_format(input) {
+                Some(format) => format,
+                None => {
+                    eprintln!("Could not detect the format of {}, use --from.", input);
+                    std::process::exit(1);
+                }
+            },
+        };
+        let to = find_format(matches.value_of("to").unwrap()).unwrap();
+        match convert(input, from, output, to) {
+            Ok(conversion) => {
+                println!("Converted {} ({}, {} books, {} verses) from {} to {} ({}).", input, conversion.identifier,
+                    conversion.books, conversion.verses, from.name, to.name, output);
+                if !conversion.unread.is_empty() {
+                    println!("Left out of the {} input: {}", from.name, conversion.unread);
                 }
+                if !conversion.unwritten.is_empty() {
+                    println!("Not representable in {}: {}", to.name, conversion.unwritten);
+                }
+            },
+            Err(e) => {
+                eprintln!("Could not convert {}: {}", input, e);
+                std::process::exit(1);
             }
         }
-        let bibles = Arc::new(Mutex::new(bibles));
+    } else if let Some(matches) = matches.subcommand_matches("validate") {
+        let scheme: Option<Versification> = matches.value_of("versification").map(|x| x.parse().unwrap());
+        let validations: Vec<Validation> = matches.values_of("FILES").unwrap()
+            .map(|path| validate(path, scheme.as_ref()))
+            .collect();
+        if matches.value_of("format") == Some("json") {
+            println!("{}", serde_json::to_string_pretty(&validations)?);
+        } else {
+            validations.iter().for_each(print_validation);
+        }
+        if validations.iter().any(|x| !x.is_valid()) {
+            std::process::exit(1);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("diff") {
+        let books: Option<BookRange> = match matches.value_of("books").map(|x| x.parse()).transpose() {
+            Ok(books) => books,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        let (old_path, new_path) = (matches.value_of("OLD").unwrap(), matches.value_of("NEW").unwrap());
+        let old = ZefaniaBible::parse(old_path).unwrap();
+        let new = ZefaniaBible::parse(new_path).unwrap();
+        let options = DiffOptions { books, ignore_tags: matches.is_present("ignore-tags") };
+        let diff = diff_bibles(&old, &new, &options);
+        let format = matches.value_of("format");
+        match format.unwrap_or("color").parse().unwrap() {
+            DiffFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+            DiffFormat::Unified => print_unified_diff(&diff, old_path, new_path),
+            // the default view leaves out the colors when piped, an explicit --format color keeps them
+            DiffFormat::Color => print_colored_diff(&diff, format.is_some() || is_terminal()),
+        }
+    } else if let Some(config) = server_config {
+        let folder = Arc::new(BibleFolder::new(&config.folder, config.translations.clone()));
+        let registry = web::Data::new(BibleRegistry::new());
+        // Load the bibles in the background, /readyz tells when they are available
+        {
+            let (registry, folder) = (registry.clone(), folder.clone());
+            actix_rt::task::spawn_blocking(move || folder.reload(&registry));
+        }
+        if !config.static_files.index.is_file() {
+            warn!("The single page app {} does not exist", config.static_files.index.display());
+        }
+
+        // Reload the bibles on SIGHUP and, with --watch, periodically
+        #[cfg(unix)]
+        {
+            let (registry, folder) = (registry.clone(), folder.clone());
+            actix_rt::spawn(async move {
+                let mut hangup = signal(SignalKind::hangup()).expect("Could not listen for SIGHUP");
+                while hangup.recv().await.is_some() {
+                    info!("Received SIGHUP, reloading bibles ...");
+                    let (registry, folder) = (registry.clone(), folder.clone());
+                    let _ = actix_rt::task::spawn_blocking(move || folder.reload(&registry)).await;
+                }
+            });
+        }
+        if let Some(seconds) = config.watch {
+            let (registry, folder) = (registry.clone(), folder.clone());
+            actix_rt::spawn(async move {
+                let mut interval = actix_rt::time::interval(Duration::from_secs(seconds.max(1)));
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    let (registry, folder) = (registry.clone(), folder.clone());
+                    let _ = actix_rt::task::spawn_blocking(move || folder.reload(&registry)).await;
+                }
+            });
+        }
+        // validated before
+        let cache_policy = config.cache_policy().unwrap();
+        let limits = config.limits();
+        let rate_limiter = config.rate_limit.as_ref().map(|x| web::Data::new(RateLimiter::new(x)));
+        let api_keys = config.api_keys().unwrap().map(web::Data::new);
+        if config.admin && !api_keys.as_ref().is_some_and(|x| x.protects(Scope::Admin)) {
+            warn!("The administration endpoints are served without api keys");
+        }
+        let (bind, port) = (config.bind.clone(), config.port);
+        let tls = config.tls.as_ref().map(|x| x.server_config().unwrap());
+        let metrics = web::Data::new(Metrics::new());
+        let schema = web::Data::new(graphql::schema(registry.clone(), limits, &config.graphql));
+        let config = Arc::new(config);
 
-        return HttpServer::new(move || {
-            // let cors = Cors::default()
-            //     // .allowed_origin("localhost")
-            //     // .allowed_origin_fn(|origin, _req_head| {
-            //     //     origin.as_bytes().ends_with(b".strongs.de")
-            //     // })
-            //     .allow_any_origin()
-            //     .max_age(3600);
-            let cors = Cors::permissive();
+        let server = HttpServer::new(move || {
+            let cors = if config.cors_permissive() {
+                Cors::permissive()
+            } else {
+                config.cors.origins.iter()
+                    .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+                    .allow_any_method()
+                    .allow_any_header()
+                    .max_age(3600)
+            };
+            let (api_docs_enabled, admin_enabled, metrics_enabled, graphql_enabled) = (config.api_docs, config.admin, config.metrics, config.graphql.enabled);
 
             ActixApp::new()
                 .wrap(cors)
-                .app_data(web::Data::new(bibles.clone()))
+                .app_data(registry.clone())
+                .app_data(web::Data::new(limits))
+                .app_data(web::Data::new(folder.clone()))
+                .app_data(web::Data::new(cache_policy.clone()))
+                .app_data(web::Data::new(config.static_files.clone()))
+                .configure(|cfg| if let Some(keys) = &api_keys {
+                    cfg.app_data(keys.clone());
+                })
+                .configure(|cfg| if let Some(limiter) = &rate_limiter {
+                    cfg.app_data(limiter.clone());
+                })
+                .wrap(middleware::Condition::new(config.cache.compress, middleware::Compress::default()))
                 // enable logger
                 .wrap(middleware::Logger::default())
-                .app_data(web::JsonConfig::default().limit(4096)) // <- limit size of the payload (global configuration)
-                .route("/api/translations.json", web::get().to(translations))
-                .route("/api/{identifier}/greek_strongs/{strong}.json", web::get().to(greek_strongs))
-                .route("/api/{identifier}/hebrew_strongs/{strong}.json", web::get().to(hebrew_strongs))
-                .route("/api/{identifier}/{book}/{chapter}.json", web::get().to(chapter))
-                .route("/api/{identifier}/{book}/{chapter}/{verse}.json", web::get().to(verse))
-                .route("/api/{identifier}/info", web::get().to(info))
-                .route("/api/{identifier}/{book}/{chapter}", web::get().to(chapter))
-                .route("/api/{identifier}/{search}", web::get().to(search))
-
-                .service(actix_files::Files::new("/build/", "./static/build"))
+                .app_data(metrics.clone())
+                .wrap(RequestMetrics)
+                .app_data(web::JsonConfig::default().limit(config.json_limit).error_handler(json_error_handler)) // <- limit size of the payload (global configuration)
+                .app_data(web::PathConfig::default().error_handler(path_error_handler))
+                .app_data(web::QueryConfig::default().error_handler(query_error_handler))
+                .route("/healthz", web::get().to(healthz))
+                .route("/readyz", web::get().to(readyz))
+                .configure(|cfg| if metrics_enabled {
+                    cfg.route("/metrics", web::get().to(metrics::metrics));
+                })
+                .configure(|cfg| if graphql_enabled {
+                    cfg.app_data(schema.clone()).configure(api_graphql);
+                })
+                .route("/api/openapi.json", web::get().to(openapi_json))
+                .configure(|cfg| if api_docs_enabled {
+                    cfg.route("/api/docs", web::get().to(api_docs));
+                })
+                .configure(|cfg| if admin_enabled {
+                    cfg.service(web::scope("/api/v1/admin").configure(api_admin));
+                })
+                .service(web::scope("/api/v1").configure(api_v1))
+                .service(web::scope("/api").configure(api_legacy))
+
+                .service(actix_files::Files::new("/build/", &config.static_files.assets))
                 .route("/", web::get().to(single_page_app))
 
                 .route("/{book}/{chapter}", web::get().to(single_page_app))
                 .route("/strongs/greek/{nr}", web::get().to(single_page_app))
                 .route("/strongs/hebrew/{nr}", web::get().to(single_page_app))
-        })
-        .bind(("0.0.0.0", port))?
-        .run()
-        .await
+        });
+        let server = match tls {
+            Some(tls) => server.bind_rustls((bind.as_str(), port), tls)?,
+            None => server.bind((bind.as_str(), port))?,
+        };
+        return server.run().await;
     }
 
     Ok(())
@@ -0,0 +1,195 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::registry::BibleRegistry;
+use crate::reload::BibleFolder;
+
+/// Upper bounds of the latency histogram buckets in seconds.
+const BUCKETS: [f64; 12] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Clone, Default)]
+struct Histogram {
+    /// Number of observations up to each bucket bound, not cumulative
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+/// Request counts and latencies, exported in the Prometheus text format at `/metrics`.
+#[derive(Default)]
+pub struct Metrics {
+    /// Requests by route pattern, method and status
+    requests: Mutex<BTreeMap<(String, String, u16), u64>>,
+    /// Latencies by route pattern and method
+    latencies: Mutex<BTreeMap<(String, String), Histogram>>,
+    /// Search latencies by translation
+    searches: Mutex<BTreeMap<String, Histogram>>,
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(i) = BUCKETS.iter().position(|x| seconds <= *x) {
+            self.buckets[i] += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    fn write(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(self.buckets.iter()) {
+            cumulative += count;
+            let _ = writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, bound, cumulative);
+        }
+        let _ = writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, self.count);
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+/// Escapes a label value of the Prometheus text format.
+fn label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn observe_request(&self, route: &str, method: &str, status: u16, duration: Duration) {
+        *self.requests.lock().unwrap_or_else(|x| x.into_inner())
+            .entry((String::from(route), String::from(method), status)).or_insert(0) += 1;
+        self.latencies.lock().unwrap_or_else(|x| x.into_inner())
+            .entry((String::from(route), String::from(method))).or_default()
+            .observe(duration);
+    }
+
+    pub fn observe_search(&self, identifier: &str, duration: Duration) {
+        self.searches.lock().unwrap_or_else(|x| x.into_inner())
+            .entry(String::from(identifier)).or_default()
+            .observe(duration);
+    }
+
+    /// Renders the metrics together with the loaded translations and parse durations.
+    pub fn render(&self, registry: &BibleRegistry, folder: &BibleFolder) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP bible_http_requests_total Number of answered http requests.\n");
+        out.push_str("# TYPE bible_http_requests_total counter\n");
+        for ((route, method, status), count) in self.requests.lock().unwrap_or_else(|x| x.into_inner()).iter() {
+            let _ = writeln!(out, "bible_http_requests_total{{route=\"{}\",method=\"{}\",status=\"{}\"}} {}", label(route), method, status, count);
+        }
+
+        out.push_str("# HELP bible_http_request_duration_seconds Latency of the http requests.\n");
+        out.push_str("# TYPE bible_http_request_duration_seconds histogram\n");
+        for ((route, method), histogram) in self.latencies.lock().unwrap_or_else(|x| x.into_inner()).iter() {
+            histogram.write(&mut out, "bible_http_request_duration_seconds", &format!("route=\"{}\",method=\"{}\"", label(route), method));
+        }
+
+        out.push_str("# HELP bible_search_duration_seconds Duration of the searches in a translation.\n");
+        out.push_str("# TYPE bible_search_duration_seconds histogram\n");
+        for (identifier, histogram) in self.searches.lock().unwrap_or_else(|x| x.into_inner()).iter() {
+            histogram.write(&mut out, "bible_search_duration_seconds", &format!("translation=\"{}\"", label(identifier)));
+        }
+
+        let bibles = registry.snapshot();
+        out.push_str("# HELP bible_translations Number of loaded translations.\n");
+        out.push_str("# TYPE bible_translations gauge\n");
+        let _ = writeln!(out, "bible_translations {}", bibles.len());
+        out.push_str("# HELP bible_translation_verses Number of verses of a loaded translation.\n");
+        out.push_str("# TYPE bible_translation_verses gauge\n");
+        let mut identifiers: Vec<&String> = bibles.keys().collect();
+        identifiers.sort();
+        for identifier in identifiers {
+            let bible = &bibles[identifier];
+            let verses: usize = bible.books.iter().flat_map(|x| x.chapters.iter()).map(|x| x.verses.len()).sum();
+            let _ = writeln!(out, "bible_translation_verses{{translation=\"{}\",name=\"{}\"}} {}", label(identifier), label(&bible.name), verses);
+        }
+
+        let status = folder.status();
+        out.push_str("# HELP bible_parse_duration_seconds Duration of parsing a loaded bible file.\n");
+        out.push_str("# TYPE bible_parse_duration_seconds gauge\n");
+        for (path, (identifier, duration)) in &status.parse_durations {
+            let _ = writeln!(out, "bible_parse_duration_seconds{{file=\"{}\",translation=\"{}\"}} {}",
+                label(&path.to_string_lossy()), label(identifier), duration.as_secs_f64());
+        }
+        out.push_str("# HELP bible_parse_failures Number of files which failed to parse in the last reload.\n");
+        out.push_str("# TYPE bible_parse_failures gauge\n");
+        let _ = writeln!(out, "bible_parse_failures {}", status.failed.len());
+        out
+    }
+}
+
+/// Records the search latency of a translation, if the app collects metrics.
+pub fn observe_search(req: &HttpRequest, identifier: &str, duration: Duration) {
+    if let Some(metrics) = req.app_data::<web::Data<Metrics>>() {
+        metrics.observe_search(identifier, duration);
+    }
+}
+
+pub async fn metrics(metrics: web::Data<Metrics>, registry: web::Data<BibleRegistry>, folder: web::Data<Arc<BibleFolder>>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(metrics.render(&registry, &folder))
+}
+
+/// Counts the requests and their latency by route pattern, e.g. `/api/v1/{identifier}/info`,
+/// so the number of series does not grow with the requested paths.
+pub struct RequestMetrics;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestMetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestMetricsMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct RequestMetricsMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let metrics = req.app_data::<web::Data<Metrics>>().cloned();
+        let route = req.match_pattern().unwrap_or_else(|| String::from("unmatched"));
+        let method = String::from(req.method().as_str());
+        let start = Instant::now();
+
+        Box::pin(async move {
+            let res = service.call(req).await?;
+            if let Some(metrics) = metrics {
+                metrics.observe_request(&route, &method, res.status().as_u16(), start.elapsed());
+            }
+            Ok(res)
+        })
+    }
+}
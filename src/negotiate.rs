@@ -0,0 +1,105 @@
+use actix_web::http::header::{self, Header};
+use actix_web::{HttpRequest, HttpResponse};
+use bible::{ChapterText, Layout, RenderOptions, render_html, render_text, render_xml};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use crate::errors::ApiError;
+
+/// Representations of the text routes, chosen by the `format` parameter or the `Accept` header.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResponseFormat {
+    Json,
+    Text,
+    Html,
+    /// Zefania XML
+    Xml,
+}
+
+/// Output options of the chapter, verse, passage and search routes.
+#[derive(Clone, Default, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FormatQuery {
+    /// `json`, `text`, `html` or `xml`, overrides the `Accept` header
+    pub format: Option<String>,
+    /// Number the verses of text and html (default: true)
+    pub verse_numbers: Option<bool>,
+    /// Strong numbers after the tagged words of text and html, `gr` elements in xml (default: false)
+    pub strongs: Option<bool>,
+    /// `lines` for a line per verse or `paragraph` for a paragraph per chapter (default: lines)
+    #[param(value_type = Option<String>)]
+    pub layout: Option<Layout>,
+}
+
+impl FromStr for ResponseFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "text" | "txt" => Ok(Self::Text),
+            "html" => Ok(Self::Html),
+            "xml" => Ok(Self::Xml),
+            _ => Err(format!("Unknown format '{}', expected json, text, html or xml.", s)),
+        }
+    }
+}
+
+impl ResponseFormat {
+    fn from_mime(essence: &str) -> Option<Self> {
+        match essence {
+            "application/json" | "*/*" | "application/*" => Some(Self::Json),
+            "text/plain" | "text/*" => Some(Self::Text),
+            "text/html" => Some(Self::Html),
+            "application/xml" | "text/xml" => Some(Self::Xml),
+            _ => None,
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Text => "text/plain; charset=utf-8",
+            Self::Html => "text/html; charset=utf-8",
+            Self::Xml => "application/xml; charset=utf-8",
+        }
+    }
+}
+
+impl FormatQuery {
+    /// The requested format, the most preferred supported type of the `Accept`
+    /// header if there is no `format` parameter, and json otherwise.
+    pub fn format(&self, req: &HttpRequest) -> Result<ResponseFormat, ApiError> {
+        if let Some(format) = &self.format {
+            return format.parse().map_err(|e| ApiError::bad_request("invalid_format", e));
+        }
+        Ok(header::Accept::parse(req).ok()
+            .and_then(|accept| accept.ranked().iter().find_map(|x| ResponseFormat::from_mime(x.essence_str())))
+            .unwrap_or(ResponseFormat::Json))
+    }
+
+    pub fn options(&self) -> RenderOptions {
+        let default = RenderOptions::default();
+        RenderOptions {
+            verse_numbers: self.verse_numbers.unwrap_or(default.verse_numbers),
+            strongs: self.strongs.unwrap_or(default.strongs),
+            layout: self.layout.unwrap_or(default.layout),
+        }
+    }
+
+    /// Answers with the value as json or its verses rendered in the requested format.
+    /// `name` is the title of html and the bible name of xml.
+    pub fn respond<'a, T: Serialize>(&self, req: &HttpRequest, value: &T, name: &str, chapters: impl FnOnce() -> Vec<ChapterText<'a>>) -> Result<HttpResponse, ApiError> {
+        let format = self.format(req)?;
+        let options = self.options();
+        let body = match format {
+            ResponseFormat::Json => return Ok(HttpResponse::Ok().append_header((header::VARY, "Accept")).json(value)),
+            ResponseFormat::Text => render_text(&chapters(), &options),
+            ResponseFormat::Html => render_html(name, &chapters(), &options),
+            ResponseFormat::Xml => render_xml(name, &chapters(), &options),
+        };
+        Ok(HttpResponse::Ok()
+            .append_header((header::VARY, "Accept"))
+            .content_type(format.content_type())
+            .body(body))
+    }
+}
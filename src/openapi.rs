@@ -0,0 +1,59 @@
+use actix_web::HttpResponse;
+use utoipa::OpenApi;
+use bible::{AssociationMeasure, Book, BookStats, Chapter, Chunk, CooccurrenceScope, Frequency, Neighbour, Related, StatsUnit,
+    StrongDictPage, StrongNumber, StrongRefSort, StrongVariant, Translation, Verse, VerseRef, VocabularyStats,
+    SearchQuery, SearchMode, SearchResult, Passage, PassagePoint, PassageQuery, PassageResult, PassageText, Comparison, ComparedVerse};
+use crate::errors::ApiError;
+use crate::reload::{Readiness, ReloadReport};
+use crate::routes;
+use crate::streaming;
+
+#[allow(deprecated)]
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Bible REST api", description = "Read, search and analyse bible translations in the Zefania XML format."),
+    paths(
+        routes::translations,
+        routes::info,
+        routes::chapter,
+        routes::verse,
+        routes::passage,
+        routes::compare_chapter,
+        routes::passage_post,
+        routes::search_v1,
+        routes::search_v1_post,
+        streaming::search_stream,
+        routes::search,
+        routes::stats,
+        routes::greek_strongs,
+        routes::hebrew_strongs,
+        routes::greek_strongs_related,
+        routes::hebrew_strongs_related,
+        routes::reload,
+        routes::healthz,
+        routes::readyz,
+    ),
+    components(schemas(
+        ApiError, ReloadReport, Readiness, Translation, Chapter, Verse, VerseRef, Chunk, StrongNumber, StrongDictPage, StrongVariant, StrongRefSort,
+        PassageQuery, Passage, PassagePoint, PassageResult, PassageText, Book, Comparison, ComparedVerse,
+        SearchQuery, SearchMode, SearchResult, Related, Neighbour, CooccurrenceScope, AssociationMeasure, VocabularyStats, Frequency, BookStats, StatsUnit,
+    )),
+    tags(
+        (name = "meta", description = "Available bible translations"),
+        (name = "text", description = "Chapters and verses"),
+        (name = "search", description = "Search in a bible translation"),
+        (name = "strongs", description = "Strong numbers and their references"),
+        (name = "analysis", description = "Word studies and vocabulary statistics"),
+        (name = "admin", description = "Server administration, only available with `serve --admin`"),
+        (name = "health", description = "Liveness and readiness of the server"),
+    )
+)]
+pub struct ApiDoc;
+
+pub async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+pub async fn api_docs() -> HttpResponse {
+    HttpResponse::Ok().content_type("text/html; charset=utf-8").body(include_str!("api_docs.html"))
+}
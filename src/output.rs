@@ -0,0 +1,136 @@
+use serde::Serialize;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(Self::Table),
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Unknown output format '{}'.", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiffFormat {
+    Color,
+    Unified,
+    Json,
+}
+
+impl FromStr for DiffFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "color" => Ok(Self::Color),
+            "unified" => Ok(Self::Unified),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Unknown diff format '{}'.", s)),
+        }
+    }
+}
+
+/// Output formats of the verses printed by `search` and `read`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VerseFormat {
+    Text,
+    /// One json array of all verses
+    Json,
+    /// One json object per line
+    Jsonl,
+    Csv,
+    Tsv,
+}
+
+impl FromStr for VerseFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "jsonl" => Ok(Self::Jsonl),
+            "csv" => Ok(Self::Csv),
+            "tsv" => Ok(Self::Tsv),
+            _ => Err(format!("Unknown output format '{}'.", s)),
+        }
+    }
+}
+
+/// A verse as printed for scripts, with 1-based numbers like the references.
+#[derive(Serialize)]
+pub struct VerseRecord {
+    pub reference: String,
+    pub book: usize,
+    pub chapter: usize,
+    pub verse: usize,
+    pub text: String,
+}
+
+impl VerseRecord {
+    fn fields(&self) -> [String; 5] {
+        [self.reference.clone(), self.book.to_string(), self.chapter.to_string(), self.verse.to_string(), self.text.clone()]
+    }
+}
+
+/// Prints the verses as json, json lines, csv or tsv with a header line.
+/// The text format is up to the command.
+pub fn print_verses(verses: &[VerseRecord], format: VerseFormat) -> serde_json::Result<()> {
+    const HEADER: [&str; 5] = ["reference", "book", "chapter", "verse", "text"];
+    match format {
+        VerseFormat::Json => println!("{}", serde_json::to_string_pretty(verses)?),
+        VerseFormat::Jsonl => for verse in verses {
+            println!("{}", serde_json::to_string(verse)?);
+        },
+        VerseFormat::Csv => {
+            println!("{}", csv_row(&HEADER));
+            for verse in verses {
+                println!("{}", csv_row(&verse.fields()));
+            }
+        },
+        VerseFormat::Tsv => {
+            println!("{}", tsv_row(&HEADER));
+            for verse in verses {
+                println!("{}", tsv_row(&verse.fields()));
+            }
+        },
+        VerseFormat::Text => {},
+    }
+    Ok(())
+}
+
+// ANSI escape sequences for the colored terminal output
+pub const RED: &str = "\x1b[31m";
+pub const GREEN: &str = "\x1b[32m";
+pub const CYAN: &str = "\x1b[36m";
+pub const BOLD: &str = "\x1b[1m";
+pub const RESET: &str = "\x1b[0m";
+
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+/// Formats one CSV record, quoting fields as described in RFC 4180.
+pub fn csv_row<T: AsRef<str>>(fields: &[T]) -> String {
+    fields.iter().map(|x| csv_field(x.as_ref())).collect::<Vec<String>>().join(",")
+}
+
+/// Formats one TSV record. Tabs and line breaks cannot be escaped, so they become spaces.
+pub fn tsv_row<T: AsRef<str>>(fields: &[T]) -> String {
+    fields.iter().map(|x| x.as_ref().replace(&['\t', '\n', '\r'][..], " ")).collect::<Vec<String>>().join("\t")
+}
@@ -0,0 +1,176 @@
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{web, Error, HttpMessage, HttpRequest, ResponseError};
+use std::collections::HashMap;
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::auth::ApiClient;
+use crate::cache::RouteClass;
+use crate::config::RateLimitConfig;
+use crate::errors::ApiError;
+
+/// Buckets of idle clients are dropped once there are more than this many.
+const MAX_BUCKETS: usize = 10000;
+
+/// Token buckets of the clients, identified by api key or ip address. Each
+/// request takes the tokens of its cost, and the buckets refill at a constant rate.
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    trust_forwarded: bool,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// The client a request is counted for, stored in the request extensions.
+#[derive(Clone)]
+struct RateLimitClient(String);
+
+/// Tokens taken by every request of a route class, searches take their estimated cost in addition.
+fn class_cost(class: RouteClass) -> usize {
+    match class {
+        RouteClass::Analysis => 5,
+        _ => 1,
+    }
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self { rate: config.rate, burst: config.burst, trust_forwarded: config.trust_forwarded, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Takes `cost` tokens from the bucket of a client, or returns the time until
+    /// the bucket holds enough tokens. A cost above the burst empties a full bucket.
+    pub fn acquire(&self, client: &str, cost: usize) -> Result<(), Duration> {
+        let cost = (cost as f64).min(self.burst);
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|x| x.into_inner());
+        if buckets.len() > MAX_BUCKETS {
+            let (rate, burst) = (self.rate, self.burst);
+            buckets.retain(|_, x| x.tokens + now.duration_since(x.updated).as_secs_f64() * rate < burst);
+        }
+        let bucket = buckets.entry(String::from(client)).or_insert(Bucket { tokens: self.burst, updated: now });
+        bucket.tokens = (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * self.rate).min(self.burst);
+        bucket.updated = now;
+        if bucket.tokens < cost {
+            return Err(Duration::from_secs_f64((cost - bucket.tokens) / self.rate));
+        }
+        bucket.tokens -= cost;
+        Ok(())
+    }
+
+    /// The api client of the request, or its ip address for anonymous requests.
+    fn client(&self, req: &ServiceRequest) -> String {
+        if let Some(client) = req.extensions().get::<ApiClient>() {
+            return format!("key:{}", client.0);
+        }
+        let info = req.connection_info();
+        let address = if self.trust_forwarded { info.realip_remote_addr() } else { info.peer_addr() };
+        // the port differs for each connection of a client
+        let address = address.unwrap_or("unknown");
+        match address.parse::<std::net::SocketAddr>() {
+            Ok(socket) => format!("ip:{}", socket.ip()),
+            Err(_) => format!("ip:{}", address),
+        }
+    }
+}
+
+/// Takes the cost of a request from the bucket of its client, which has been
+/// checked by the `RateLimit` middleware. Without a `RateLimiter` every request passes.
+pub fn charge(req: &HttpRequest, cost: usize) -> Result<(), ApiError> {
+    ClientBucket::of(req).charge(cost)
+}
+
+/// The bucket of the client of a request, for charging costs which are known only
+/// while executing it, e.g. the searches of a GraphQL query.
+#[derive(Clone)]
+pub struct ClientBucket(Option<(web::Data<RateLimiter>, String)>);
+
+impl ClientBucket {
+    pub fn of(req: &HttpRequest) -> Self {
+        let limiter = req.app_data::<web::Data<RateLimiter>>().cloned();
+        let client = req.extensions().get::<RateLimitClient>().map(|x| x.0.clone());
+        Self(limiter.zip(client))
+    }
+
+    pub fn charge(&self, cost: usize) -> Result<(), ApiError> {
+        match &self.0 {
+            Some((limiter, client)) => limiter.acquire(client, cost).map_err(ApiError::too_many_requests),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Answers requests of clients which used up their tokens with 429 Too Many
+/// Requests and a `Retry-After` header, without running the handler.
+pub struct RateLimit {
+    class: RouteClass,
+}
+
+impl RateLimit {
+    pub fn new(class: RouteClass) -> Self {
+        Self { class }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware { service: Rc::new(service), class: self.class }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    class: RouteClass,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let allowed = match req.app_data::<web::Data<RateLimiter>>() {
+            Some(limiter) => {
+                let client = limiter.client(&req);
+                let allowed = limiter.acquire(&client, class_cost(self.class));
+                req.extensions_mut().insert(RateLimitClient(client));
+                allowed
+            },
+            None => Ok(()),
+        };
+
+        Box::pin(async move {
+            if let Err(retry_after) = allowed {
+                let res = ApiError::too_many_requests(retry_after).error_response();
+                return Ok(req.into_response(res).map_into_right_body());
+            }
+            Ok(service.call(req).await?.map_into_left_body())
+        })
+    }
+}
@@ -0,0 +1,152 @@
+use bible::{ChapterText, Layout, RenderOptions, VersePart, verse_parts};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use crate::output::{BOLD, CYAN, RED, RESET};
+
+/// How the `read` command prints verses on the terminal.
+#[derive(Clone, Copy, Debug)]
+pub struct ReadOptions {
+    /// Verse numbers, strong numbers and layout, as for the plain text of the api
+    pub render: RenderOptions,
+    /// Column to wrap the lines at, `None` to not wrap
+    pub width: Option<usize>,
+    /// Bold headings, colored numbers and red letters with ANSI escape sequences
+    pub color: bool,
+}
+
+/// A word of the output with the escape sequences around it, and its visible length.
+struct Word {
+    text: String,
+    len: usize,
+}
+
+pub fn is_terminal() -> bool {
+    unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 }
+}
+
+/// The number of columns of the terminal, `None` if stdout is no terminal.
+pub fn terminal_width() -> Option<usize> {
+    if !is_terminal() {
+        return None;
+    }
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    match unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } {
+        0 if size.ws_col > 0 => Some(size.ws_col as usize),
+        _ => None,
+    }
+}
+
+impl Word {
+    fn new(text: &str, style: Option<&str>) -> Self {
+        let len = text.chars().count();
+        match style {
+            Some(style) => Self { text: format!("{}{}{}", style, text, RESET), len },
+            None => Self { text: String::from(text), len },
+        }
+    }
+}
+
+/// The words of a verse, starting with its number right-aligned to `number_width` columns.
+fn verse_words(book: usize, verse: usize, chunks: &[bible::Chunk], number_width: usize, options: &ReadOptions) -> Vec<Word> {
+    let style = |style: &'static str| if options.color { Some(style) } else { None };
+    let mut words: Vec<Word> = vec![];
+    for part in verse_parts(book, verse, chunks, &options.render) {
+        match part {
+            VersePart::Number(number) => words.push(Word::new(&format!("{:>width$}", number, width = number_width), style(CYAN))),
+            VersePart::Word { text, red } => words.push(Word::new(text, if red { style(RED) } else { None })),
+            VersePart::Strong(key) => if let Some(last) = words.last_mut() {
+                let key = Word::new(&format!("[{}]", key), style(CYAN));
+                last.text.push_str(&key.text);
+                last.len += key.len;
+            },
+        }
+    }
+    words
+}
+
+/// Joins the words to lines of at most `width` columns, indenting the following
+/// lines by `indent` columns. Longer words get a line of their own.
+fn wrap(words: &[Word], width: Option<usize>, indent: usize) -> String {
+    let mut out = String::new();
+    let mut column = 0;
+    for (i, word) in words.iter().enumerate() {
+        if i > 0 {
+            if width.is_some_and(|width| column + 1 + word.len > width) {
+                out.push('\n');
+                out.push_str(&" ".repeat(indent));
+                column = indent;
+            } else {
+                out.push(' ');
+                column += 1;
+            }
+        }
+        out.push_str(&word.text);
+        column += word.len;
+    }
+    out
+}
+
+/// Formats the chapters with a heading each, a verse per line with the following
+/// lines indented below the text, or a paragraph per chapter.
+pub fn format_chapters(chapters: &[ChapterText], options: &ReadOptions) -> String {
+    let mut out = String::new();
+    for (i, chapter) in chapters.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        if options.color {
+            out.push_str(&format!("{}{}{}\n", BOLD, chapter.title(), RESET));
+        } else {
+            out.push_str(&format!("{}\n", chapter.title()));
+        }
+        match options.render.layout {
+            Layout::Lines => {
+                // align the texts of one and two digit verse numbers
+                let number_width = chapter.verses.iter().map(|(x, _)| (x + 1).to_string().len()).max().unwrap_or(0);
+                let indent = if options.render.verse_numbers { number_width + 1 } else { 0 };
+                for (verse, chunks) in &chapter.verses {
+                    out.push_str(&wrap(&verse_words(chapter.book, *verse, chunks, number_width, options), options.width, indent));
+                    out.push('\n');
+                }
+            },
+            Layout::Paragraph => {
+                let words: Vec<Word> = chapter.verses.iter()
+                    .flat_map(|(verse, chunks)| verse_words(chapter.book, *verse, chunks, 0, options))
+                    .collect();
+                out.push_str(&wrap(&words, options.width, 0));
+                out.push('\n');
+            },
+        }
+    }
+    out
+}
+
+/// Shows the text through `$PAGER` (default: `less`) if stdout is a terminal,
+/// and prints it otherwise or if the pager cannot be started.
+pub fn page(text: &str, pager: bool) -> io::Result<()> {
+    if pager && is_terminal() {
+        let pager = std::env::var("PAGER").ok().filter(|x| !x.trim().is_empty()).unwrap_or_else(|| String::from("less"));
+        // through the shell, as the pager may be given with arguments
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&pager).stdin(Stdio::piped());
+        if std::env::var_os("LESS").is_none() {
+            // quit if the text fits on the screen and keep the colors
+            command.env("LESS", "FRX");
+        }
+        if let Ok(mut child) = command.spawn() {
+            if let Some(mut stdin) = child.stdin.take() {
+                // the pager may be quit before reading everything
+                let _ = stdin.write_all(text.as_bytes());
+            }
+            // 127: the shell could not find the pager
+            if child.wait()?.code() != Some(127) {
+                return Ok(());
+            }
+        }
+    }
+    let mut stdout = io::stdout();
+    match stdout.write_all(text.as_bytes()) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
@@ -0,0 +1,49 @@
+use bible::{Bible, Translation};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+pub type BibleMap = HashMap<String, Arc<Bible>>;
+
+/// The loaded bibles, indexed by identifier. Readers take a snapshot of the
+/// immutable map, which only holds the lock for cloning an `Arc`, so requests
+/// never wait for each other. Updates build a new map and swap it in.
+#[derive(Default)]
+pub struct BibleRegistry {
+    bibles: RwLock<Arc<BibleMap>>,
+}
+
+impl BibleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(&self) -> Arc<BibleMap> {
+        match self.bibles.read() {
+            Ok(bibles) => bibles.clone(),
+            // the map is only ever replaced as a whole, so it is consistent after a panic, too
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    pub fn get(&self, identifier: &str) -> Option<Arc<Bible>> {
+        self.snapshot().get(identifier).cloned()
+    }
+
+    /// The translations ordered by identifier.
+    pub fn translations(&self) -> Vec<Translation> {
+        let mut translations: Vec<Translation> = self.snapshot().values().map(|x| x.get_translation()).collect();
+        translations.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+        translations
+    }
+
+    /// Applies `update` to a copy of the map and swaps it in atomically.
+    pub fn update<F: FnOnce(&mut BibleMap)>(&self, update: F) {
+        let mut bibles = match self.bibles.write() {
+            Ok(bibles) => bibles,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let mut map = BibleMap::clone(&bibles);
+        update(&mut map);
+        *bibles = Arc::new(map);
+    }
+}
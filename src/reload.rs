@@ -0,0 +1,228 @@
+use bible::{Bible, BibleParser, ZefaniaBible};
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use crate::config::TranslationConfig;
+use crate::registry::BibleRegistry;
+use std::time::{Duration, Instant, SystemTime};
+use utoipa::ToSchema;
+
+/// The bible xml files of the `serve --folder`, together with the state they
+/// had when they were loaded, so a reload only parses new or changed files.
+pub struct BibleFolder {
+    pub folder: PathBuf,
+    /// Options applied to the translations after parsing
+    translations: HashMap<String, TranslationConfig>,
+    files: Mutex<HashMap<PathBuf, LoadedFile>>,
+    /// Files which could not be loaded, only parsed again once they change
+    failed_files: Mutex<HashMap<PathBuf, FailedFile>>,
+    /// Outcome of the last reload, readable while the next one is running
+    status: RwLock<FolderStatus>,
+}
+
+struct LoadedFile {
+    modified: Option<SystemTime>,
+    len: u64,
+    identifier: String,
+    parse_duration: Duration,
+}
+
+struct FailedFile {
+    modified: Option<SystemTime>,
+    len: u64,
+    error: String,
+    /// The identifier if it is already used by another file, which may go away
+    duplicate: Option<String>,
+}
+
+#[derive(Clone, Default)]
+pub struct FolderStatus {
+    /// Whether the folder has been loaded at least once
+    pub loaded: bool,
+    pub failed: Vec<String>,
+    /// Identifier and parse duration of the loaded files
+    pub parse_durations: BTreeMap<PathBuf, (String, Duration)>,
+}
+
+/// Whether the server has loaded all bibles and can answer requests.
+#[derive(Serialize, ToSchema)]
+pub struct Readiness {
+    pub ready: bool,
+    /// Number of loaded translations
+    pub translations: usize,
+    /// Files which could not be parsed
+    pub failed: Vec<String>,
+    /// Configured translations which are not loaded
+    pub missing: Vec<String>,
+}
+
+/// Outcome of a reload, listing the identifiers of the translations.
+#[derive(Default, Serialize, ToSchema)]
+pub struct ReloadReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    /// Files which could not be parsed or whose identifier is already used by another
+    /// file, a previously loaded version stays in use
+    pub failed: Vec<String>,
+}
+
+fn parse_file(path: &Path) -> Result<Bible, String> {
+    let path = path.to_string_lossy();
+    // the parser panics on malformed xml, which must not take down the server
+    match std::panic::catch_unwind(|| ZefaniaBible::parse(&path)) {
+        Ok(Ok(bible)) if bible.books.is_empty() => Err(format!("{}: contains no bible books", path)),
+        Ok(Ok(bible)) => Ok(bible),
+        Ok(Err(e)) => Err(format!("{}: {}", path, e)),
+        Err(_) => Err(format!("{}: malformed xml", path)),
+    }
+}
+
+fn file_state(path: &Path) -> Option<(Option<SystemTime>, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok(), meta.len()))
+}
+
+impl BibleFolder {
+    pub fn new(folder: &Path, translations: HashMap<String, TranslationConfig>) -> Self {
+        Self { folder: folder.to_path_buf(), translations, files: Mutex::new(HashMap::new()), failed_files: Mutex::new(HashMap::new()), status: RwLock::new(FolderStatus::default()) }
+    }
+
+    pub fn status(&self) -> FolderStatus {
+        self.status.read().unwrap_or_else(|x| x.into_inner()).clone()
+    }
+
+    /// Ready once the folder is loaded without failures and every translation of the configuration is loaded.
+    pub fn readiness(&self, registry: &BibleRegistry) -> Readiness {
+        let status = self.status();
+        let bibles = registry.snapshot();
+        let mut missing: Vec<String> = self.translations.keys().filter(|x| !bibles.contains_key(*x)).cloned().collect();
+        missing.sort();
+        Readiness {
+            ready: status.loaded && status.failed.is_empty() && missing.is_empty(),
+            translations: bibles.len(),
+            failed: status.failed,
+            missing,
+        }
+    }
+
+    fn apply_options(&self, bible: &mut Bible) {
+        if let Some(options) = self.translations.get(&bible.identifier) {
+            if let Some(name) = &options.name {
+                bible.name = name.clone();
+            }
+            if let Some(versification) = options.versification() {
+                bible.versification = versification;
+            }
+        }
+    }
+
+    fn xml_files(&self) -> Vec<PathBuf> {
+        match fs::read_dir(&self.folder) {
+            Ok(entries) => {
+                let mut paths: Vec<PathBuf> = entries.flatten()
+                    .map(|x| x.path())
+                    .filter(|x| x.to_string_lossy().ends_with("xml"))
+                    .collect();
+                // the first of several files with the same identifier is kept
+                paths.sort();
+                paths
+            },
+            Err(e) => {
+                warn!("Could not read bibles folder {}: {}", self.folder.display(), e);
+                vec![]
+            }
+        }
+    }
+
+    /// Parses new and changed files and removes the bibles of deleted files. The
+    /// files are parsed in the background and swapped into the registry at once,
+    /// requests keep the snapshot of the bibles they started with.
+    pub fn reload(&self, registry: &BibleRegistry) -> ReloadReport {
+        // only one reload at a time
+        let mut files = self.files.lock().unwrap();
+        let mut failed_files = self.failed_files.lock().unwrap();
+        let mut report = ReloadReport::default();
+
+        let paths = self.xml_files();
+        let deleted: Vec<PathBuf> = files.keys().filter(|x| !paths.contains(x)).cloned().collect();
+        // duplicates are tried again once the file using their identifier is gone
+        failed_files.retain(|path, x| paths.contains(path) && x.duplicate.as_ref()
+            .is_none_or(|id| files.iter().any(|(p, f)| p != path && !deleted.contains(p) && f.identifier == *id)));
+        let mut owners: HashMap<String, PathBuf> = files.iter()
+            .filter(|(path, _)| !deleted.contains(path))
+            .map(|(path, x)| (x.identifier.clone(), path.clone()))
+            .collect();
+        let mut parsed = vec![];
+        for path in &paths {
+            let (modified, len) = match file_state(path) {
+                Some(state) => state,
+                None => continue,
+            };
+            if let Some(loaded) = files.get(path) {
+                if loaded.modified == modified && loaded.len == len {
+                    continue;
+                }
+            }
+            if let Some(failed) = failed_files.get(path) {
+                if failed.modified == modified && failed.len == len {
+                    report.failed.push(failed.error.clone());
+                    continue;
+                }
+            }
+            let start = Instant::now();
+            let bible = parse_file(path).map_err(|e| (e, None)).and_then(|bible| match owners.get(&bible.identifier) {
+                Some(owner) if owner != path => Err((format!("{}: identifier {} is already used by {}",
+                    path.display(), bible.identifier, owner.display()), Some(bible.identifier))),
+                _ => Ok(bible),
+            });
+            match bible {
+                Ok(mut bible) => {
+                    self.apply_options(&mut bible);
+                    if let Some(loaded) = files.get(path) {
+                        owners.remove(&loaded.identifier);
+                    }
+                    owners.insert(bible.identifier.clone(), path.clone());
+                    failed_files.remove(path);
+                    let file = LoadedFile { modified, len, identifier: bible.identifier.clone(), parse_duration: start.elapsed() };
+                    parsed.push((path.clone(), file, bible));
+                },
+                Err((error, duplicate)) => {
+                    report.failed.push(error.clone());
+                    failed_files.insert(path.clone(), FailedFile { modified, len, error, duplicate });
+                },
+            }
+        }
+
+        registry.update(|bibles| {
+            for path in deleted {
+                if let Some(loaded) = files.remove(&path) {
+                    bibles.remove(&loaded.identifier);
+                    report.removed.push(loaded.identifier);
+                }
+            }
+            for (path, file, bible) in parsed {
+                match files.insert(path, file) {
+                    Some(old) => {
+                        bibles.remove(&old.identifier);
+                        report.updated.push(bible.identifier.clone());
+                    },
+                    None => report.added.push(bible.identifier.clone()),
+                }
+                bibles.insert(bible.identifier.clone(), Arc::new(bible));
+            }
+        });
+
+        *self.status.write().unwrap_or_else(|x| x.into_inner()) = FolderStatus {
+            loaded: true,
+            failed: report.failed.clone(),
+            parse_durations: files.iter().map(|(path, x)| (path.clone(), (x.identifier.clone(), x.parse_duration))).collect(),
+        };
+        info!("Reloaded bibles from {}: added {:?}, updated {:?}, removed {:?}, failed {:?}",
+            self.folder.display(), report.added, report.updated, report.removed, report.failed);
+        report
+    }
+}
@@ -1,80 +1,599 @@
-use actix_web::{web, HttpResponse, Result};
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::DefaultHeaders;
 use actix_files as fs;
-use std::path::PathBuf;
-use std::sync::{Mutex, Arc};
-use bible::{Bible, BibleSearcher, Translation};
+use std::sync::Arc;
+use std::time::Instant;
+use bible::{BOOKS, Bible, BibleSearcher, ChapterText, CompareQuery, Passage, PassagePoint, PassageQuery, MAX_WINDOW, RelatedQuery, SearchQuery, StatsQuery, StrongKey, StrongRefQuery, compare, get_passages, passages_verse_count, related, vocabulary_stats};
+use crate::auth::{RequireScope, Scope};
+use crate::cache::{HttpCache, RouteClass};
+use crate::config::StaticConfig;
+use crate::errors::ApiError;
+use crate::graphql::graphql;
+use crate::ratelimit::{charge, RateLimit};
+use crate::registry::BibleRegistry;
+use crate::metrics::observe_search;
+use crate::negotiate::FormatQuery;
+use crate::reload::BibleFolder;
+use crate::streaming::search_stream;
 
-pub async fn translations(bibles: web::Data<Arc<Mutex<Vec<Bible>>>>) -> HttpResponse {
-    let translations: Vec<Translation> = bibles.lock().unwrap().iter().map(|x| x.get_translation()).collect();
-    HttpResponse::Ok().json(translations)
+/// Limits of the result sizes served by the api.
+#[derive(Clone, Copy)]
+pub struct ApiLimits {
+    pub max_passage_verses: usize,
+    /// Maximum number of verses or references in a page, larger pages are truncated
+    pub max_page_size: usize,
+    /// Searches with a higher `SearchQuery::estimated_cost` are rejected
+    pub max_search_cost: usize,
 }
 
-pub async fn info(bibles: web::Data<Arc<Mutex<Vec<Bible>>>>, info: web::Path<String>) -> HttpResponse {
-    let identifier = info.clone();
-    if let Some(bible) = bibles.lock().unwrap().iter().filter(|x| x.identifier == identifier).nth(0) {
-        HttpResponse::Ok().json(bible.get_translation())
-    } else {
-        HttpResponse::BadRequest().json(String::from("Could not find bible translation with given identifier."))
+pub const DEFAULT_MAX_PASSAGE_VERSES: usize = 2000;
+pub const DEFAULT_MAX_PAGE_SIZE: usize = 500;
+pub const DEFAULT_MAX_SEARCH_COST: usize = 8;
+/// Tokens charged for an analysis over all books, on top of its route class.
+const ANALYSIS_COST: usize = 8;
+
+impl Default for ApiLimits {
+    fn default() -> Self {
+        Self {
+            max_passage_verses: DEFAULT_MAX_PASSAGE_VERSES,
+            max_page_size: DEFAULT_MAX_PAGE_SIZE,
+            max_search_cost: DEFAULT_MAX_SEARCH_COST,
+        }
     }
 }
 
-pub async fn chapter(bibles: web::Data<Arc<Mutex<Vec<Bible>>>>, info: web::Path<(String, usize, usize)>) -> HttpResponse {
-    let identifier = info.0.clone();
-    let book = info.1.clone();
-    let chapter = info.2.clone();
-    if let Some(bible) = bibles.lock().unwrap().iter().filter(|x| x.identifier == identifier).nth(0) {
-        HttpResponse::Ok().json(bible.get_chapter(book, chapter))
-    } else {
-        HttpResponse::BadRequest().json(String::from("Could not find bible translation with given identifier."))
+impl ApiLimits {
+    /// The requested page size capped to `max_page_size`.
+    fn page_size(&self, limit: Option<usize>) -> usize {
+        limit.unwrap_or(self.max_page_size).min(self.max_page_size)
     }
+
+    /// Rejects searches whose estimated cost exceeds `max_search_cost` and takes the
+    /// cost from the rate limit of the client.
+    pub fn check_search(&self, req: &HttpRequest, query: &SearchQuery) -> Result<(), ApiError> {
+        let cost = match query.estimated_cost() {
+            Ok(cost) => cost,
+            // malformed queries are rejected by the search itself
+            Err(_) => return Ok(()),
+        };
+        if cost > self.max_search_cost {
+            return Err(ApiError::query_too_expensive(cost, self.max_search_cost));
+        }
+        charge(req, cost)
+    }
+}
+
+pub fn find_bible(registry: &BibleRegistry, identifier: &str) -> Result<Arc<Bible>, ApiError> {
+    registry.get(identifier).ok_or_else(|| ApiError::translation_not_found(identifier))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/translations.json",
+    tag = "meta",
+    responses(
+        (status = 200, description = "All loaded bible translations", body = [Translation]),
+    )
+)]
+pub async fn translations(registry: web::Data<BibleRegistry>) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(registry.translations()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/{identifier}/info",
+    tag = "meta",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+    ),
+    responses(
+        (status = 200, description = "The bible translation", body = Translation),
+        (status = 404, description = "Unknown translation", body = ApiError),
+    )
+)]
+pub async fn info(registry: web::Data<BibleRegistry>, info: web::Path<String>) -> Result<HttpResponse, ApiError> {
+    let bible = find_bible(&registry, &info)?;
+    Ok(HttpResponse::Ok().json(bible.get_translation()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/{identifier}/{book}/{chapter}.json",
+    tag = "text",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+        ("book" = usize, Path, description = "0-based book number"),
+        ("chapter" = usize, Path, description = "0-based chapter number"),
+        FormatQuery,
+    ),
+    responses(
+        (status = 200, description = "The chapter with all verses, as text, html or Zefania XML if requested", body = Chapter),
+        (status = 400, description = "Unknown format", body = ApiError),
+        (status = 404, description = "Unknown translation, book or chapter", body = ApiError),
+    )
+)]
+pub async fn chapter(req: HttpRequest, registry: web::Data<BibleRegistry>, info: web::Path<(String, usize, usize)>, format: web::Query<FormatQuery>) -> Result<HttpResponse, ApiError> {
+    let (identifier, book, chapter) = info.into_inner();
+    let bible = find_bible(&registry, &identifier)?;
+    let chapter = bible.get_book(book)
+        .ok_or_else(|| ApiError::book_not_found(book))?
+        .get_chapter(chapter)
+        .ok_or_else(|| ApiError::chapter_not_found(book, chapter))?;
+    format.respond(&req, chapter, &bible.name, || vec![ChapterText::new(book, chapter)])
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/{identifier}/{book}/{chapter}/{verse}.json",
+    tag = "text",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+        ("book" = usize, Path, description = "0-based book number"),
+        ("chapter" = usize, Path, description = "0-based chapter number"),
+        ("verse" = usize, Path, description = "0-based verse number"),
+        FormatQuery,
+    ),
+    responses(
+        (status = 200, description = "The verse, as text, html or Zefania XML if requested", body = Verse),
+        (status = 400, description = "Unknown format", body = ApiError),
+        (status = 404, description = "Unknown translation, book, chapter or verse", body = ApiError),
+    )
+)]
+pub async fn verse(req: HttpRequest, registry: web::Data<BibleRegistry>, info: web::Path<(String, usize, usize, usize)>, format: web::Query<FormatQuery>) -> Result<HttpResponse, ApiError> {
+    let (identifier, book, chapter_nr, verse) = info.into_inner();
+    let bible = find_bible(&registry, &identifier)?;
+    let verse = bible.get_book(book)
+        .ok_or_else(|| ApiError::book_not_found(book))?
+        .get_chapter(chapter_nr)
+        .ok_or_else(|| ApiError::chapter_not_found(book, chapter_nr))?
+        .get_verse(verse)
+        .ok_or_else(|| ApiError::verse_not_found(book, chapter_nr, verse))?;
+    format.respond(&req, verse, &bible.name, || vec![ChapterText { book, chapter: chapter_nr, verses: vec![(verse.verse, verse.chunks.as_slice())] }])
 }
 
-pub async fn verse(bibles: web::Data<Arc<Mutex<Vec<Bible>>>>, info: web::Path<(String, usize, usize, usize)>) -> HttpResponse {
-    let identifier = info.0.clone();
-    let book = info.1.clone();
-    let chapter = info.2.clone();
-    let verse = info.3.clone();
-    if let Some(bible) = bibles.lock().unwrap().iter().filter(|x| x.identifier == identifier).nth(0) {
-        HttpResponse::Ok().json(bible.get_verse(book, chapter, verse))
+#[utoipa::path(
+    get,
+    path = "/api/v1/compare/{book}/{chapter}",
+    tag = "text",
+    params(
+        ("book" = usize, Path, description = "0-based book number"),
+        ("chapter" = usize, Path, description = "0-based chapter number in the english numbering"),
+        CompareQuery,
+    ),
+    responses(
+        (status = 200, description = "The chapter of all translations aligned verse by verse", body = Comparison),
+        (status = 404, description = "Unknown translation or book", body = ApiError),
+    )
+)]
+pub async fn compare_chapter(registry: web::Data<BibleRegistry>, info: web::Path<(usize, usize)>, query: web::Query<CompareQuery>) -> Result<HttpResponse, ApiError> {
+    let (book, chapter) = info.into_inner();
+    if book >= BOOKS.len() {
+        return Err(ApiError::book_not_found(book));
+    }
+    let identifiers = query.identifiers();
+    let selected: Vec<Arc<Bible>> = if identifiers.is_empty() {
+        let bibles = registry.snapshot();
+        let mut selected: Vec<Arc<Bible>> = bibles.values().cloned().collect();
+        selected.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+        selected
     } else {
-        HttpResponse::BadRequest().json(String::from("Could not find bible translation with given identifier."))
+        identifiers.iter().map(|x| find_bible(&registry, x)).collect::<Result<Vec<_>, _>>()?
+    };
+    let point = PassagePoint { book, chapter: Some(chapter), verse: None };
+    let comparison = compare(&selected.iter().map(|x| x.as_ref()).collect::<Vec<&Bible>>(), &Passage::new(point, point));
+    if comparison.verses.is_empty() {
+        return Err(ApiError::chapter_not_found(book, chapter));
     }
+    Ok(HttpResponse::Ok().json(comparison))
 }
 
-pub async fn search(bibles: web::Data<Arc<Mutex<Vec<Bible>>>>, info: web::Path<(String, String,)>) -> HttpResponse {
-    let identifier = info.0.clone();
-    let search = info.1.clone();
-    if let Some(bible) = bibles.lock().unwrap().iter().filter(|x| x.identifier == identifier).nth(0) {
-        HttpResponse::Ok().json(bible.search_parallel(&search).unwrap())
-    } else {
-        HttpResponse::BadRequest().json(String::from("Could not find bible translation with given identifier."))
+fn run_passage(req: &HttpRequest, registry: &BibleRegistry, limits: &ApiLimits, identifier: &str, query: &PassageQuery, format: &FormatQuery) -> Result<HttpResponse, ApiError> {
+    let passages = query.passages().map_err(|e| ApiError::bad_request("invalid_reference", e.to_string()))?;
+    if passages.is_empty() {
+        return Err(ApiError::bad_request("invalid_reference", String::from("At least one passage is required.")));
+    }
+    let bible = find_bible(registry, identifier)?;
+    let verse_count = passages_verse_count(&bible, &passages);
+    if verse_count > limits.max_passage_verses {
+        return Err(
+            ApiError::bad_request("passage_too_large", String::from("The passages contain too many verses."))
+                .with_details(serde_json::json!({ "verses": verse_count, "max_verses": limits.max_passage_verses }))
+        );
     }
+    let result = get_passages(&bible, &passages);
+    format.respond(req, &result, &bible.name, || ChapterText::from_passages(&result.passages))
 }
 
-pub async fn greek_strongs(bibles: web::Data<Arc<Mutex<Vec<Bible>>>>, info: web::Path<(String, usize)>) -> HttpResponse {
-    strongs(bibles, info, true).await
+#[utoipa::path(
+    get,
+    path = "/api/v1/{identifier}/passage",
+    tag = "text",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+        ("ref" = String, Query, description = "Passages separated by `;`, e.g. `Matthew 5:1-7:29; John 3,16`"),
+        FormatQuery,
+    ),
+    responses(
+        (status = 200, description = "The verses of the passages, grouped by book and chapter, as text, html or Zefania XML if requested", body = PassageResult),
+        (status = 400, description = "Malformed reference or too many verses", body = ApiError),
+        (status = 404, description = "Unknown translation", body = ApiError),
+    )
+)]
+pub async fn passage(req: HttpRequest, registry: web::Data<BibleRegistry>, limits: web::Data<ApiLimits>, info: web::Path<String>, query: web::Query<PassageQuery>, format: web::Query<FormatQuery>) -> Result<HttpResponse, ApiError> {
+    run_passage(&req, &registry, &limits, &info, &query, &format)
 }
 
-pub async fn hebrew_strongs(bibles: web::Data<Arc<Mutex<Vec<Bible>>>>, info: web::Path<(String, usize)>) -> HttpResponse {
-    strongs(bibles, info, false).await
+#[utoipa::path(
+    post,
+    path = "/api/v1/{identifier}/passage",
+    tag = "text",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+        FormatQuery,
+    ),
+    request_body = PassageQuery,
+    responses(
+        (status = 200, description = "The verses of the passages, grouped by book and chapter, as text, html or Zefania XML if requested", body = PassageResult),
+        (status = 400, description = "Malformed passages or too many verses", body = ApiError),
+        (status = 404, description = "Unknown translation", body = ApiError),
+    )
+)]
+pub async fn passage_post(req: HttpRequest, registry: web::Data<BibleRegistry>, limits: web::Data<ApiLimits>, info: web::Path<String>, query: web::Json<PassageQuery>, format: web::Query<FormatQuery>) -> Result<HttpResponse, ApiError> {
+    run_passage(&req, &registry, &limits, &info, &query, &format)
 }
 
-pub async fn strongs(bibles: web::Data<Arc<Mutex<Vec<Bible>>>>, info: web::Path<(String, usize)>, greek: bool) -> HttpResponse {
-    let identifier = info.0.clone();
-    let strong = info.1.clone();
-    if let Some(bible) = bibles.lock().unwrap().iter().filter(|x| x.identifier == identifier).nth(0) {
-        let strong_dict = if greek { bible.greek_strong_dict.get(&strong) } else { bible.hebrew_strong_dict.get(&strong) };
-        if let Some(dict) = strong_dict {
-            HttpResponse::Ok().json(dict.get_with_chunks(&bible))
-        } else {
-            HttpResponse::BadRequest().json(String::from("Could not find strong numbers."))
-        }
+#[utoipa::path(
+    get,
+    path = "/api/{identifier}/{search}",
+    tag = "search",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+        ("search" = String, Path, description = "Search term, words in quotes are searched as phrase"),
+        FormatQuery,
+    ),
+    responses(
+        (status = 200, description = "The verses containing all words, as text, html or Zefania XML if requested", body = [VerseRef]),
+        (status = 400, description = "Malformed or too expensive search term", body = ApiError),
+        (status = 404, description = "Unknown translation", body = ApiError),
+        (status = 429, description = "Rate limit exceeded, retry after the seconds of the `Retry-After` header", body = ApiError),
+    )
+)]
+#[deprecated(note = "use the query parameter based search_v1 endpoint")]
+pub async fn search(req: HttpRequest, registry: web::Data<BibleRegistry>, limits: web::Data<ApiLimits>, info: web::Path<(String, String,)>, format: web::Query<FormatQuery>) -> Result<HttpResponse, ApiError> {
+    let (identifier, search) = info.into_inner();
+    let bible = find_bible(&registry, &identifier)?;
+    limits.check_search(&req, &SearchQuery::new(&search))?;
+    let start = Instant::now();
+    let found = bible.search_parallel(&search);
+    observe_search(&req, &identifier, start.elapsed());
+    match found {
+        Ok(mut res) => {
+            // the legacy search has no paging, so only the total tells about truncated results
+            let total = res.len();
+            res.truncate(limits.max_page_size);
+            let mut response = format.respond(&req, &res, &bible.name, || ChapterText::group(&res))?;
+            response.headers_mut().insert(HeaderName::from_static("x-total-count"), HeaderValue::from(total));
+            Ok(response)
+        },
+        Err(e) if e.is::<shellwords::MismatchedQuotes>() => Err(
+            ApiError::bad_request("invalid_query", String::from("The search term contains mismatched quotes."))
+                .with_details(serde_json::json!({ "search": search }))
+        ),
+        Err(e) => Err(ApiError::internal(e.to_string())),
+    }
+}
+
+fn run_search(req: &HttpRequest, registry: &BibleRegistry, limits: &ApiLimits, identifier: &str, query: &SearchQuery, format: &FormatQuery) -> Result<HttpResponse, ApiError> {
+    if query.q.trim().is_empty() {
+        return Err(ApiError::bad_request("invalid_query", String::from("The search text must not be empty.")));
+    }
+    let bible = find_bible(registry, identifier)?;
+    limits.check_search(req, query)?;
+    let query = SearchQuery { limit: Some(limits.page_size(query.limit)), ..query.clone() };
+    let start = Instant::now();
+    let found = bible.search_with(&query);
+    observe_search(req, identifier, start.elapsed());
+    match found {
+        Ok(res) => format.respond(req, &res, &bible.name, || ChapterText::group(&res.verses)),
+        Err(e) if e.is::<shellwords::MismatchedQuotes>() => Err(
+            ApiError::bad_request("invalid_query", String::from("The search text contains mismatched quotes."))
+                .with_details(serde_json::json!({ "q": query.q }))
+        ),
+        Err(e) => Err(ApiError::internal(e.to_string())),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/{identifier}/search",
+    tag = "search",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+        SearchQuery,
+        FormatQuery,
+    ),
+    responses(
+        (status = 200, description = "The page of verses matching the query, as text, html or Zefania XML if requested", body = SearchResult),
+        (status = 400, description = "Malformed or too expensive query", body = ApiError),
+        (status = 404, description = "Unknown translation", body = ApiError),
+        (status = 429, description = "Rate limit exceeded, retry after the seconds of the `Retry-After` header", body = ApiError),
+    )
+)]
+pub async fn search_v1(req: HttpRequest, registry: web::Data<BibleRegistry>, limits: web::Data<ApiLimits>, info: web::Path<String>, query: web::Query<SearchQuery>, format: web::Query<FormatQuery>) -> Result<HttpResponse, ApiError> {
+    run_search(&req, &registry, &limits, &info, &query, &format)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/{identifier}/search",
+    tag = "search",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+        FormatQuery,
+    ),
+    request_body = SearchQuery,
+    responses(
+        (status = 200, description = "The page of verses matching the query, as text, html or Zefania XML if requested", body = SearchResult),
+        (status = 400, description = "Malformed or too expensive query", body = ApiError),
+        (status = 404, description = "Unknown translation", body = ApiError),
+        (status = 429, description = "Rate limit exceeded, retry after the seconds of the `Retry-After` header", body = ApiError),
+    )
+)]
+pub async fn search_v1_post(req: HttpRequest, registry: web::Data<BibleRegistry>, limits: web::Data<ApiLimits>, info: web::Path<String>, query: web::Json<SearchQuery>, format: web::Query<FormatQuery>) -> Result<HttpResponse, ApiError> {
+    run_search(&req, &registry, &limits, &info, &query, &format)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/{identifier}/stats",
+    tag = "analysis",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+        StatsQuery,
+    ),
+    responses(
+        (status = 200, description = "Vocabulary statistics", body = VocabularyStats),
+        (status = 400, description = "Invalid query parameters", body = ApiError),
+        (status = 404, description = "Unknown translation", body = ApiError),
+        (status = 429, description = "Rate limit exceeded, retry after the seconds of the `Retry-After` header", body = ApiError),
+    )
+)]
+pub async fn stats(req: HttpRequest, registry: web::Data<BibleRegistry>, limits: web::Data<ApiLimits>, info: web::Path<String>, query: web::Query<StatsQuery>) -> Result<HttpResponse, ApiError> {
+    let bible = find_bible(&registry, &info)?;
+    let books = query.books.map(|x| x.to.saturating_sub(x.from) + 1).unwrap_or(BOOKS.len()).min(BOOKS.len());
+    charge(&req, (ANALYSIS_COST * books).div_ceil(BOOKS.len()).max(1))?;
+    let query = StatsQuery { top: query.top.map(|x| x.min(limits.max_page_size)), ..query.into_inner() };
+    let mut stats = web::block(move || vocabulary_stats(&bible, &query)).await
+        .map_err(|e| ApiError::internal(e.to_string()))?;
+    // hapax_count still tells about the truncated ones
+    if let Some(hapax_legomena) = stats.hapax_legomena.as_mut() {
+        hapax_legomena.truncate(limits.max_page_size);
+    }
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/{identifier}/greek_strongs/{strong}.json",
+    tag = "strongs",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+        ("strong" = usize, Path, description = "Strong number without language prefix"),
+        StrongRefQuery,
+    ),
+    responses(
+        (status = 200, description = "The references of the greek strong number", body = StrongDictPage),
+        (status = 400, description = "Invalid query parameters", body = ApiError),
+        (status = 404, description = "Unknown translation or strong number", body = ApiError),
+    )
+)]
+pub async fn greek_strongs(registry: web::Data<BibleRegistry>, limits: web::Data<ApiLimits>, info: web::Path<(String, usize)>, query: web::Query<StrongRefQuery>) -> Result<HttpResponse, ApiError> {
+    strongs(registry, limits, info, query, true).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/{identifier}/hebrew_strongs/{strong}.json",
+    tag = "strongs",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+        ("strong" = usize, Path, description = "Strong number without language prefix"),
+        StrongRefQuery,
+    ),
+    responses(
+        (status = 200, description = "The references of the hebrew strong number", body = StrongDictPage),
+        (status = 400, description = "Invalid query parameters", body = ApiError),
+        (status = 404, description = "Unknown translation or strong number", body = ApiError),
+    )
+)]
+pub async fn hebrew_strongs(registry: web::Data<BibleRegistry>, limits: web::Data<ApiLimits>, info: web::Path<(String, usize)>, query: web::Query<StrongRefQuery>) -> Result<HttpResponse, ApiError> {
+    strongs(registry, limits, info, query, false).await
+}
+
+pub async fn strongs(registry: web::Data<BibleRegistry>, limits: web::Data<ApiLimits>, info: web::Path<(String, usize)>, query: web::Query<StrongRefQuery>, greek: bool) -> Result<HttpResponse, ApiError> {
+    let (identifier, strong) = info.into_inner();
+    let key = StrongKey::new(greek, strong);
+    let bible = find_bible(&registry, &identifier)?;
+    let dict = key.get_dict_entry(&bible).ok_or_else(|| ApiError::strong_not_found(&key.to_string()))?;
+    let query = StrongRefQuery { limit: Some(limits.page_size(query.limit)), ..query.into_inner() };
+    Ok(HttpResponse::Ok().json(dict.get_page(&bible, &query)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/{identifier}/greek_strongs/{strong}/related",
+    tag = "analysis",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+        ("strong" = usize, Path, description = "Strong number without language prefix"),
+        RelatedQuery,
+    ),
+    responses(
+        (status = 200, description = "The strong numbers co-occurring with the greek strong number", body = Related),
+        (status = 400, description = "Invalid query parameters", body = ApiError),
+        (status = 404, description = "Unknown translation or strong number", body = ApiError),
+        (status = 429, description = "Rate limit exceeded, retry after the seconds of the `Retry-After` header", body = ApiError),
+    )
+)]
+pub async fn greek_strongs_related(req: HttpRequest, registry: web::Data<BibleRegistry>, limits: web::Data<ApiLimits>, info: web::Path<(String, usize)>, query: web::Query<RelatedQuery>) -> Result<HttpResponse, ApiError> {
+    strongs_related(req, registry, limits, info, query, true).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/{identifier}/hebrew_strongs/{strong}/related",
+    tag = "analysis",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+        ("strong" = usize, Path, description = "Strong number without language prefix"),
+        RelatedQuery,
+    ),
+    responses(
+        (status = 200, description = "The strong numbers co-occurring with the hebrew strong number", body = Related),
+        (status = 400, description = "Invalid query parameters", body = ApiError),
+        (status = 404, description = "Unknown translation or strong number", body = ApiError),
+        (status = 429, description = "Rate limit exceeded, retry after the seconds of the `Retry-After` header", body = ApiError),
+    )
+)]
+pub async fn hebrew_strongs_related(req: HttpRequest, registry: web::Data<BibleRegistry>, limits: web::Data<ApiLimits>, info: web::Path<(String, usize)>, query: web::Query<RelatedQuery>) -> Result<HttpResponse, ApiError> {
+    strongs_related(req, registry, limits, info, query, false).await
+}
+
+pub async fn strongs_related(req: HttpRequest, registry: web::Data<BibleRegistry>, limits: web::Data<ApiLimits>, info: web::Path<(String, usize)>, query: web::Query<RelatedQuery>, greek: bool) -> Result<HttpResponse, ApiError> {
+    let (identifier, strong) = info.into_inner();
+    let key = StrongKey::new(greek, strong);
+    let bible = find_bible(&registry, &identifier)?;
+    key.get_dict_entry(&bible).ok_or_else(|| ApiError::strong_not_found(&key.to_string()))?;
+    if query.window.is_some_and(|x| x > MAX_WINDOW) {
+        return Err(ApiError::bad_request("invalid_query", format!("The window must not be larger than {} words.", MAX_WINDOW)));
+    }
+    charge(&req, ANALYSIS_COST)?;
+    let query = RelatedQuery { limit: query.limit.map(|x| x.min(limits.max_page_size)), ..query.into_inner() };
+    let related = web::block(move || related(&bible, key, &query)).await
+        .map_err(|e| ApiError::internal(e.to_string()))?;
+    Ok(HttpResponse::Ok().json(related))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/reload",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The translations added, updated and removed by the reload", body = ReloadReport),
+        (status = 401, description = "Missing or unknown api key", body = ApiError),
+        (status = 403, description = "The api key lacks the admin scope", body = ApiError),
+    )
+)]
+pub async fn reload(registry: web::Data<BibleRegistry>, folder: web::Data<Arc<BibleFolder>>) -> Result<HttpResponse, ApiError> {
+    let (registry, folder) = (registry.into_inner(), folder.get_ref().clone());
+    let report = web::block(move || folder.reload(&registry)).await
+        .map_err(|e| ApiError::internal(e.to_string()))?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Liveness probe, answers as long as the server runs.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses(
+        (status = 200, description = "The server is running"),
+    )
+)]
+pub async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+/// Readiness probe, answers 503 while bibles are loading, failed to parse or are missing.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "All bibles are loaded", body = Readiness),
+        (status = 503, description = "The bibles are still loading, failed to parse or configured translations are missing", body = Readiness),
+    )
+)]
+pub async fn readyz(registry: web::Data<BibleRegistry>, folder: web::Data<Arc<BibleFolder>>) -> HttpResponse {
+    let readiness = folder.readiness(&registry);
+    if readiness.ready {
+        HttpResponse::Ok().json(readiness)
     } else {
-        HttpResponse::BadRequest().json(String::from("Could not find bible translation with given identifier."))
+        HttpResponse::ServiceUnavailable().json(readiness)
     }
 }
 
-pub async fn single_page_app() -> Result<fs::NamedFile> {
-    let path: PathBuf = PathBuf::from("./static/index.html");
-    Ok(fs::NamedFile::open(path)?)
-}
\ No newline at end of file
+/// The GraphQL api, counted as search for api keys and rate limits as it can search.
+pub fn api_graphql(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/graphql")
+        .wrap(RateLimit::new(RouteClass::Search))
+        .wrap(RequireScope::new(Scope::Search))
+        .route(web::post().to(graphql)));
+}
+
+/// Administrative routes, mounted at `/api/v1/admin` if enabled with `serve --admin`.
+pub fn api_admin(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/reload").wrap(RequireScope::new(Scope::Admin)).route(web::post().to(reload)));
+}
+
+/// Routes of the current api version, mounted at `/api/v1`. They are registered
+/// before the routes of the first version, whose `/{identifier}/{book}/{chapter}`
+/// would take their paths otherwise.
+pub fn api_v1(cfg: &mut web::ServiceConfig) {
+    let resource = |path: &str, class: RouteClass| web::resource(path)
+        .wrap(HttpCache::new(class))
+        .wrap(RateLimit::new(class))
+        .wrap(RequireScope::new(class.into()));
+    cfg.service(resource("/compare/{book}/{chapter}", RouteClass::Text).route(web::get().to(compare_chapter)))
+        .service(resource("/{identifier}/greek_strongs/{strong}/related", RouteClass::Analysis).route(web::get().to(greek_strongs_related)))
+        .service(resource("/{identifier}/hebrew_strongs/{strong}/related", RouteClass::Analysis).route(web::get().to(hebrew_strongs_related)))
+        .service(resource("/{identifier}/stats", RouteClass::Analysis).route(web::get().to(stats)))
+        .service(resource("/{identifier}/passage", RouteClass::Text)
+            .route(web::get().to(passage))
+            .route(web::post().to(passage_post)))
+        .service(resource("/{identifier}/search/stream", RouteClass::Search).route(web::get().to(search_stream)))
+        .service(resource("/{identifier}/search", RouteClass::Search)
+            .route(web::get().to(search_v1))
+            .route(web::post().to(search_v1_post)))
+        .configure(api_first_version);
+}
+
+/// The routes the unversioned api had from the start, served at `/api` and `/api/v1`.
+fn api_first_version(cfg: &mut web::ServiceConfig) {
+    let resource = |path: &str, class: RouteClass| web::resource(path)
+        .wrap(HttpCache::new(class))
+        .wrap(RateLimit::new(class))
+        .wrap(RequireScope::new(class.into()));
+    cfg.service(resource("/translations.json", RouteClass::Meta).route(web::get().to(translations)))
+        .service(resource("/{identifier}/greek_strongs/{strong}.json", RouteClass::Strongs).route(web::get().to(greek_strongs)))
+        .service(resource("/{identifier}/hebrew_strongs/{strong}.json", RouteClass::Strongs).route(web::get().to(hebrew_strongs)))
+        .service(resource("/{identifier}/{book}/{chapter}.json", RouteClass::Text).route(web::get().to(chapter)))
+        .service(resource("/{identifier}/{book}/{chapter}/{verse}.json", RouteClass::Text).route(web::get().to(verse)))
+        .service(resource("/{identifier}/info", RouteClass::Meta).route(web::get().to(info)))
+        .service(resource("/{identifier}/{book}/{chapter}", RouteClass::Text).route(web::get().to(chapter)));
+}
+
+/// The unversioned api at `/api`, kept as deprecated alias of its routes in `/api/v1`
+/// for existing clients, including the search with the term as path segment. Routes
+/// added later are only served below `/api/v1`, as paths like `/{identifier}/stats`
+/// are searches for a word here.
+#[allow(deprecated)]
+pub fn api_legacy(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(DefaultHeaders::new()
+                .add(("Deprecation", "true"))
+                .add(("Link", "</api/v1>; rel=\"successor-version\"")))
+            .configure(api_first_version)
+            .service(web::resource("/{identifier}/{search}")
+                .wrap(HttpCache::new(RouteClass::Search))
+                .wrap(RateLimit::new(RouteClass::Search))
+                .wrap(RequireScope::new(Scope::Search))
+                .route(web::get().to(search)))
+    );
+}
+
+pub async fn single_page_app(static_files: web::Data<StaticConfig>) -> Result<fs::NamedFile> {
+    Ok(fs::NamedFile::open(&static_files.index)?)
+}
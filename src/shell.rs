@@ -0,0 +1,201 @@
+use bible::{Bible, BibleSearcher, BookRange, ChapterText, Passage, RenderOptions, SearchQuery, StrongKey, StrongRefQuery,
+    BOOKS, compare, format_reference};
+use std::path::PathBuf;
+use std::time::Instant;
+use crate::lineedit::LineEditor;
+use crate::output::{BOLD, CYAN, RESET};
+use crate::reader::{ReadOptions, format_chapters, is_terminal, terminal_width};
+use crate::{chunks_text, print_comparison};
+
+const COMMANDS: &[&str] = &["read", "next", "prev", "search", "strong", "use", "translations", "compare", "help", "quit"];
+
+/// Maximum number of search results and strong references printed at once.
+const MAX_RESULTS: usize = 100;
+
+const HELP: &str = "Commands:
+  read <passage>       read a passage, e.g. read John 3 or just John 3,16-21
+  next, prev           read the next or previous chapter
+  search <text>        search the translation, e.g. search liebe or search NT: \"im anfang\"
+  strong <number>      show the renderings and references of a strong number, e.g. strong G26
+  use <identifier>     switch to another loaded translation
+  translations         list the loaded translations
+  compare <passage>    show a passage of all loaded translations verse by verse
+  help                 show this help
+  quit                 leave the shell (or Ctrl-D)";
+
+/// Interactive session over bibles which are parsed only once.
+pub struct Shell {
+    bibles: Vec<Bible>,
+    current: usize,
+    /// The last chapter read, to continue with `next` and `prev`
+    position: Option<(usize, usize)>,
+}
+
+/// Completes the command, the translation of `use` and book names anywhere else,
+/// also those with several words like `Song of Solomon`.
+fn complete(before: &str, identifiers: &[String]) -> (usize, Vec<String>) {
+    let starts_with = |candidate: &str, prefix: &str| candidate.to_lowercase().starts_with(&prefix.to_lowercase());
+    let chars: Vec<char> = before.chars().collect();
+    if let Some(rest) = before.strip_prefix("use ") {
+        let candidates = identifiers.iter().filter(|x| starts_with(x, rest.trim_start())).cloned().collect();
+        return (chars.len() - rest.trim_start().chars().count(), candidates);
+    }
+    let word_starts = (0..chars.len()).filter(|&i| i == 0 || chars[i - 1].is_whitespace() || chars[i - 1] == ':');
+    for start in word_starts {
+        let prefix: String = chars[start..].iter().collect();
+        let mut candidates: Vec<String> = BOOKS.iter().filter(|x| starts_with(x, &prefix)).map(|x| String::from(*x)).collect();
+        if start == 0 {
+            candidates.extend(COMMANDS.iter().filter(|x| starts_with(x, &prefix)).map(|x| String::from(*x)));
+        }
+        if !candidates.is_empty() {
+            return (start, candidates);
+        }
+    }
+    (chars.len(), vec![])
+}
+
+/// The reference of a result, highlighted if stdout is a terminal.
+fn highlight(reference: String) -> String {
+    if is_terminal() {
+        format!("{}{}{}{}", BOLD, CYAN, reference, RESET)
+    } else {
+        reference
+    }
+}
+
+impl Shell {
+    pub fn new(bibles: Vec<Bible>) -> Self {
+        Self { bibles, current: 0, position: None }
+    }
+
+    fn bible(&self) -> &Bible {
+        &self.bibles[self.current]
+    }
+
+    pub fn run(&mut self) {
+        let identifiers: Vec<String> = self.bibles.iter().map(|x| x.identifier.clone()).collect();
+        let history = std::env::var_os("HOME").map(|x| PathBuf::from(x).join(".bible-cli-history"));
+        let mut editor = LineEditor::new(history, move |before| complete(before, &identifiers));
+        println!("Type a passage like John 3 to read it, help for all commands.");
+        loop {
+            let line = match editor.read_line(&format!("{}> ", self.bible().identifier)) {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    break;
+                },
+            };
+            let line = line.trim();
+            let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let args = args.trim();
+            let result = match command.to_lowercase().as_str() {
+                "" => Ok(()),
+                "quit" | "exit" | "q" => break,
+                "help" | "?" => {
+                    println!("{}", HELP);
+                    Ok(())
+                },
+                "read" => self.read(args),
+                "next" | "n" => self.turn(true),
+                "prev" | "p" => self.turn(false),
+                "search" | "/" => self.search(args),
+                "strong" => self.strong(args),
+                "use" => self.switch(args),
+                "translations" | "list" => {
+                    for (i, bible) in self.bibles.iter().enumerate() {
+                        println!("{} {:<12} {}", if i == self.current { "*" } else { " " }, bible.identifier, bible.name);
+                    }
+                    Ok(())
+                },
+                "compare" => self.compare(args),
+                _ => self.read(line).map_err(|e| format!("{} Type help for the commands.", e)),
+            };
+            if let Err(e) = result {
+                eprintln!("{}", e);
+            }
+        }
+    }
+
+    fn read(&mut self, reference: &str) -> Result<(), String> {
+        let passage: Passage = reference.parse().map_err(|e: bible::ParseReferenceError| e.to_string())?;
+        let text = passage.get_text(self.bible());
+        let chapters = ChapterText::from_passages(std::slice::from_ref(&text));
+        let last = chapters.last()
+            .map(|x| (x.book, x.chapter))
+            .ok_or_else(|| format!("Could not find {} in {}.", reference, self.bible().identifier))?;
+        let options = ReadOptions { render: RenderOptions::default(), width: terminal_width(), color: is_terminal() };
+        print!("{}", format_chapters(&chapters, &options));
+        self.position = Some(last);
+        Ok(())
+    }
+
+    /// Reads the chapter after or before the last one read, crossing book boundaries.
+    fn turn(&mut self, forward: bool) -> Result<(), String> {
+        let (book, chapter) = self.position.ok_or_else(|| String::from("Read a chapter first, e.g. John 3."))?;
+        let chapters = self.bible().books.iter()
+            .flat_map(|x| x.chapters.iter().map(move |chapter| (x.nr, chapter.chapter)));
+        let target = if forward {
+            chapters.filter(|x| *x > (book, chapter)).min()
+        } else {
+            chapters.filter(|x| *x < (book, chapter)).max()
+        };
+        let (book, chapter) = target.ok_or_else(|| String::from(if forward { "This is the last chapter." } else { "This is the first chapter." }))?;
+        self.read(&format!("{} {}", BOOKS[book], chapter + 1))
+    }
+
+    /// Searches for the text, optionally in a book range given before a colon, e.g. `NT: liebe`.
+    fn search(&self, args: &str) -> Result<(), String> {
+        let mut query = SearchQuery::new(args);
+        if let Some((scope, text)) = args.split_once(':') {
+            if let Ok(scope) = scope.parse::<BookRange>() {
+                query = SearchQuery { scope: Some(scope), ..SearchQuery::new(text.trim()) };
+            }
+        }
+        if query.q.trim().is_empty() {
+            return Err(String::from("Search for what? e.g. search liebe"));
+        }
+        query.limit = Some(MAX_RESULTS);
+        let start = Instant::now();
+        let result = self.bible().search_with(&query).map_err(|e| e.to_string())?;
+        println!("Found {} verses in {}ms", result.total, start.elapsed().as_millis());
+        for verse in &result.verses {
+            println!("  {} {}", highlight(format_reference(verse.book, verse.chapter, verse.verse)), chunks_text(verse));
+        }
+        if result.total > result.verses.len() {
+            println!("  ... and {} more", result.total - result.verses.len());
+        }
+        Ok(())
+    }
+
+    fn strong(&self, args: &str) -> Result<(), String> {
+        let key: StrongKey = args.parse().map_err(|e: bible::ParseReferenceError| e.to_string())?;
+        let entry = key.get_dict_entry(self.bible())
+            .ok_or_else(|| format!("Could not find strong number {} in {}.", key, self.bible().identifier))?;
+        let page = entry.get_page(self.bible(), &StrongRefQuery { limit: Some(MAX_RESULTS), ..Default::default() });
+        println!("{} occurs {} times:", key, page.total);
+        for v in &page.variants {
+            println!("  {} ({})", v.variant, v.count);
+        }
+        for verse in &page.refs {
+            println!("  {} {}", highlight(format_reference(verse.book, verse.chapter, verse.verse)), chunks_text(verse));
+        }
+        if page.total > page.refs.len() {
+            println!("  ... and {} more", page.total - page.refs.len());
+        }
+        Ok(())
+    }
+
+    fn switch(&mut self, identifier: &str) -> Result<(), String> {
+        self.current = self.bibles.iter().position(|x| x.identifier.eq_ignore_ascii_case(identifier))
+            .ok_or_else(|| format!("Unknown translation '{}', see translations.", identifier))?;
+        println!("Using {} ({})", self.bible().identifier, self.bible().name);
+        Ok(())
+    }
+
+    fn compare(&self, reference: &str) -> Result<(), String> {
+        let passage: Passage = reference.parse().map_err(|e: bible::ParseReferenceError| e.to_string())?;
+        print_comparison(&compare(&self.bibles.iter().collect::<Vec<&Bible>>(), &passage));
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use actix_web::http::header;
+use actix_web::web::Bytes;
+use actix_web::{web, HttpRequest, HttpResponse};
+use bible::{Bible, BibleSearcher, SearchQuery, VerseRef, BOOKS};
+use serde::Serialize;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use crate::errors::ApiError;
+use crate::metrics::Metrics;
+use crate::registry::BibleRegistry;
+use crate::routes::{find_bible, ApiLimits};
+
+/// Books searched ahead of a client which reads the events slower than they are found.
+const BUFFERED_BOOKS: usize = 4;
+
+/// An event of a streamed search, sent as server-sent event named by its type or as json line.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum SearchEvent {
+    /// The matching verses of a book, only sent for books with matches
+    Verses {
+        book: usize,
+        name: &'static str,
+        verses: Vec<VerseRef>,
+    },
+    /// The last event, `complete` is false if the search stopped at the limit
+    Summary {
+        total: usize,
+        books: usize,
+        complete: bool,
+        duration_ms: u128,
+    },
+}
+
+impl SearchEvent {
+    fn encode(&self, sse: bool) -> Bytes {
+        let data = serde_json::to_string(self).unwrap_or_default();
+        match (sse, self) {
+            (true, Self::Verses { .. }) => Bytes::from(format!("event: verses\ndata: {}\n\n", data)),
+            (true, Self::Summary { .. }) => Bytes::from(format!("event: summary\ndata: {}\n\n", data)),
+            (false, _) => Bytes::from(data + "\n"),
+        }
+    }
+}
+
+/// Searches the books of the scope one after the other and sends their matches,
+/// stopping early if the client went away.
+fn run(bible: &Bible, query: &SearchQuery, events: mpsc::Sender<SearchEvent>) {
+    let start = Instant::now();
+    let mut skip = query.offset.unwrap_or(0);
+    let mut remaining = query.limit.unwrap_or(usize::MAX);
+    let (mut total, mut books, mut complete) = (0, 0, true);
+    for book in bible.books.iter().filter(|x| query.scope.iter().all(|scope| scope.contains(x.nr))) {
+        if remaining == 0 {
+            complete = false;
+            break;
+        }
+        let mut verses = match bible.search_book(query, book.nr) {
+            Ok(verses) => verses,
+            Err(_) => break,
+        };
+        books += 1;
+        total += verses.len();
+        let skipped = skip.min(verses.len());
+        verses.drain(..skipped);
+        skip -= skipped;
+        if verses.len() > remaining {
+            verses.truncate(remaining);
+            complete = false;
+        }
+        remaining -= verses.len();
+        if !verses.is_empty() && events.blocking_send(SearchEvent::Verses { book: book.nr, name: BOOKS.get(book.nr).copied().unwrap_or(""), verses }).is_err() {
+            return;
+        }
+    }
+    let _ = events.blocking_send(SearchEvent::Summary { total, books, complete, duration_ms: start.elapsed().as_millis() });
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/{identifier}/search/stream",
+    tag = "search",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the bible translation, e.g. ELB1905STR"),
+        SearchQuery,
+    ),
+    responses(
+        (status = 200, description = "The matching verses book by book, followed by a summary. Sent as server-sent events \
+            with `Accept: text/event-stream`, otherwise as json lines", content_type = "application/x-ndjson", body = String),
+        (status = 400, description = "Malformed or too expensive query", body = ApiError),
+        (status = 404, description = "Unknown translation", body = ApiError),
+        (status = 429, description = "Rate limit exceeded, retry after the seconds of the `Retry-After` header", body = ApiError),
+    )
+)]
+pub async fn search_stream(req: HttpRequest, registry: web::Data<BibleRegistry>, limits: web::Data<ApiLimits>, info: web::Path<String>, query: web::Query<SearchQuery>) -> Result<HttpResponse, ApiError> {
+    let identifier = info.into_inner();
+    let query = query.into_inner();
+    if query.q.trim().is_empty() {
+        return Err(ApiError::bad_request("invalid_query", String::from("The search text must not be empty.")));
+    }
+    let bible = find_bible(&registry, &identifier)?;
+    if query.estimated_cost().is_err() {
+        return Err(ApiError::bad_request("invalid_query", String::from("The search text contains mismatched quotes."))
+            .with_details(serde_json::json!({ "q": query.q })));
+    }
+    limits.check_search(&req, &query)?;
+
+    let sse = req.headers().get(header::ACCEPT)
+        .and_then(|x| x.to_str().ok())
+        .is_some_and(|x| x.contains("text/event-stream"));
+    let metrics = req.app_data::<web::Data<Metrics>>().cloned();
+    let (sender, receiver) = mpsc::channel(BUFFERED_BOOKS);
+    actix_rt::task::spawn_blocking(move || {
+        let start = Instant::now();
+        run(&bible, &query, sender);
+        if let Some(metrics) = metrics {
+            metrics.observe_search(&identifier, start.elapsed());
+        }
+    });
+
+    let body = futures_util::stream::unfold(receiver, move |mut receiver| async move {
+        let event = receiver.recv().await?;
+        Some((Ok::<_, actix_web::Error>(event.encode(sse)), receiver))
+    });
+    Ok(HttpResponse::Ok()
+        .append_header((header::VARY, "Accept"))
+        .content_type(if sse { "text/event-stream" } else { "application/x-ndjson" })
+        .streaming(body))
+}